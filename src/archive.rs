@@ -0,0 +1,375 @@
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::Path;
+use tar::Archive as TarArchive;
+use xz2::read::XzDecoder;
+use zip::read::ZipArchive;
+
+/// Number of bytes needed to recognize every format, tar's magic ends at byte 262
+pub const SNIFF_LEN: usize = 262;
+
+/// Archive formats that can be recognized from the first bytes of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarBz2,
+    Rar,
+}
+
+impl ArchiveFormat {
+    /// Function to recognize an archive by its contents instead of its extension
+    ///
+    /// #Arguments
+    /// - path to the file
+    ///
+    /// #Returns
+    /// - the ArchiveFormat, or None if the file can't be read or is not an archive
+    ///
+    /// gzip, xz and bzip2 streams are assumed to hold a tarball, that is how mods are
+    /// shipped in those formats. The tar backend rejects a single compressed file.
+    pub fn sniff<P: AsRef<Path>>(path: P) -> Option<ArchiveFormat> {
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        File::open(path)
+            .ok()?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut header)
+            .ok()?;
+
+        ArchiveFormat::sniff_bytes(&header)
+    }
+
+    /// Function to recognize an archive from the start of its contents
+    ///
+    /// #Arguments
+    /// - the first SNIFF_LEN bytes of the file, or all of it if it is shorter
+    ///
+    /// #Returns
+    /// - the ArchiveFormat, or None if the bytes are not the start of an archive
+    pub fn sniff_bytes(header: &[u8]) -> Option<ArchiveFormat> {
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if header.starts_with(b"7z\xBC\xAF\x27\x1C") {
+            Some(ArchiveFormat::SevenZip)
+        } else if header.starts_with(b"\x1F\x8B") {
+            Some(ArchiveFormat::TarGz)
+        } else if header.starts_with(b"\xFD7zXZ\x00") {
+            Some(ArchiveFormat::TarXz)
+        } else if header.starts_with(b"BZh") {
+            Some(ArchiveFormat::TarBz2)
+        } else if header.starts_with(b"Rar!\x1A\x07") {
+            Some(ArchiveFormat::Rar)
+        } else if is_tar_header(header) {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Function to get the backend that reads this format
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - the ArchiveBackend for the format
+    /// - an error message if the format can't be read
+    pub fn backend(&self) -> Result<Box<dyn ArchiveBackend>, String> {
+        match self {
+            ArchiveFormat::Zip => Ok(Box::new(ZipBackend)),
+            ArchiveFormat::SevenZip => Ok(Box::new(SevenZipBackend)),
+            ArchiveFormat::Tar => Ok(Box::new(TarBackend(Compression::None))),
+            ArchiveFormat::TarGz => Ok(Box::new(TarBackend(Compression::Gzip))),
+            ArchiveFormat::TarXz => Ok(Box::new(TarBackend(Compression::Xz))),
+            ArchiveFormat::TarBz2 => Ok(Box::new(TarBackend(Compression::Bzip2))),
+            // there is no pure-Rust rar reader yet
+            ArchiveFormat::Rar => {
+                Err("rar archives are not supported, extract it and import the files".to_string())
+            }
+        }
+    }
+}
+
+/// Checks for the ustar magic that GNU tar, bsdtar and every other current tar write
+fn is_tar_header(header: &[u8]) -> bool {
+    header.get(257..262) == Some(b"ustar")
+}
+
+/// Reads the files stored in one archive format
+pub trait ArchiveBackend {
+    /// Function to read every file in an archive
+    ///
+    /// #Arguments
+    /// - path to the archive
+    /// - callback given the path of each file inside the archive and a reader for its contents
+    ///
+    /// #Returns
+    /// - an io error if the archive is corrupt or the callback fails
+    ///
+    /// Folders are not passed to the callback.
+    fn for_each_file(
+        &self,
+        path: &Path,
+        on_file: &mut dyn FnMut(&str, &mut dyn Read) -> std::io::Result<()>,
+    ) -> std::io::Result<()>;
+}
+
+/// .zip files
+struct ZipBackend;
+
+impl ArchiveBackend for ZipBackend {
+    fn for_each_file(
+        &self,
+        path: &Path,
+        on_file: &mut dyn FnMut(&str, &mut dyn Read) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.name().to_string();
+            on_file(&name, &mut file)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// .7z files
+struct SevenZipBackend;
+
+impl ArchiveBackend for SevenZipBackend {
+    fn for_each_file(
+        &self,
+        path: &Path,
+        on_file: &mut dyn FnMut(&str, &mut dyn Read) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let mut archive =
+            SevenZReader::open(path, Password::empty()).map_err(std::io::Error::other)?;
+
+        archive
+            .for_each_entries(|entry, reader| {
+                if !entry.is_directory() {
+                    on_file(entry.name(), reader)?;
+                }
+                Ok(true)
+            })
+            .map_err(std::io::Error::other)
+    }
+}
+
+/// How the stream around a tarball is compressed
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Bzip2,
+}
+
+/// .tar, .tar.gz, .tar.xz and .tar.bz2 files
+struct TarBackend(Compression);
+
+impl ArchiveBackend for TarBackend {
+    fn for_each_file(
+        &self,
+        path: &Path,
+        on_file: &mut dyn FnMut(&str, &mut dyn Read) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let file = BufReader::new(File::open(path)?);
+        let mut stream: Box<dyn Read> = match self.0 {
+            Compression::None => Box::new(file),
+            Compression::Gzip => Box::new(GzDecoder::new(file)),
+            Compression::Xz => Box::new(XzDecoder::new(file)),
+            Compression::Bzip2 => Box::new(BzDecoder::new(file)),
+        };
+
+        // gzip, xz and bzip2 also compress single files, which have no tar header
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        stream
+            .by_ref()
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut header)?;
+        if !is_tar_header(&header) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "not a tar archive, decompress it and import the file inside",
+            ));
+        }
+
+        let mut archive = TarArchive::new(Cursor::new(header).chain(stream));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            // links and device files are not mods
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().into_owned();
+            on_file(&name, &mut entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+    use std::fs;
+    use std::io::Write;
+
+    /// Builds a tarball holding the given files
+    fn tar_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// Reads every file of an archive with the backend of its sniffed format
+    fn read_files(path: &Path) -> std::io::Result<Vec<(String, String)>> {
+        let format = ArchiveFormat::sniff(path).expect("not an archive");
+        let mut files = Vec::new();
+        format
+            .backend()
+            .unwrap()
+            .for_each_file(path, &mut |name, reader| {
+                let mut contents = String::new();
+                reader.read_to_string(&mut contents)?;
+                files.push((name.to_string(), contents));
+                Ok(())
+            })?;
+        Ok(files)
+    }
+
+    #[test]
+    fn recognizes_archives_by_their_first_bytes() {
+        let cases: [(&[u8], ArchiveFormat); 7] = [
+            (b"PK\x03\x04rest", ArchiveFormat::Zip),
+            (b"PK\x05\x06", ArchiveFormat::Zip),
+            (b"7z\xBC\xAF\x27\x1C\x00\x04", ArchiveFormat::SevenZip),
+            (b"\x1F\x8B\x08\x00", ArchiveFormat::TarGz),
+            (b"\xFD7zXZ\x00\x00", ArchiveFormat::TarXz),
+            (b"BZh91AY", ArchiveFormat::TarBz2),
+            (b"Rar!\x1A\x07\x01\x00", ArchiveFormat::Rar),
+        ];
+        for (header, format) in cases {
+            assert_eq!(ArchiveFormat::sniff_bytes(header), Some(format));
+        }
+
+        let tar = tar_bytes(&[("maps.wad", b"PWAD")]);
+        assert_eq!(
+            ArchiveFormat::sniff_bytes(&tar[..SNIFF_LEN]),
+            Some(ArchiveFormat::Tar)
+        );
+    }
+
+    #[test]
+    fn ignores_files_that_are_not_archives() {
+        for header in [&b""[..], b"PWAD\x00\x00", b"PK", b"Patch File for DeHackEd"] {
+            assert_eq!(ArchiveFormat::sniff_bytes(header), None);
+        }
+        // a header cut short before the tar magic
+        assert_eq!(ArchiveFormat::sniff_bytes(&[0u8; 100]), None);
+    }
+
+    #[test]
+    fn reads_the_files_of_a_tarball() {
+        let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&tar_bytes(&[
+            ("mapset/maps.wad", b"PWAD"),
+            ("mapset/mapset.txt", b"Title : Mapset"),
+        ]))
+        .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "imp-porter-archive-mapset-{}.tar.gz",
+            std::process::id()
+        ));
+        fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        assert_eq!(ArchiveFormat::sniff(&path), Some(ArchiveFormat::TarGz));
+        assert_eq!(
+            read_files(&path).unwrap(),
+            vec![
+                ("mapset/maps.wad".to_string(), "PWAD".to_string()),
+                (
+                    "mapset/mapset.txt".to_string(),
+                    "Title : Mapset".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_a_compressed_file_that_is_not_a_tarball() {
+        let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(b"PWAD\x00\x00\x00\x00\x0C\x00\x00\x00")
+            .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "imp-porter-archive-maps-{}.wad.gz",
+            std::process::id()
+        ));
+        fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        assert_eq!(ArchiveFormat::sniff(&path), Some(ArchiveFormat::TarGz));
+        let error = read_files(&path).unwrap_err();
+        assert!(error.to_string().contains("not a tar archive"), "{}", error);
+    }
+
+    #[test]
+    fn reads_the_files_of_a_7z_archive() {
+        let mut writer = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, contents) in [
+            ("mapset/maps.wad", &b"PWAD"[..]),
+            ("mapset/mapset.txt", b"Title : Mapset"),
+        ] {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            writer.push_archive_entry(entry, Some(contents)).unwrap();
+        }
+        let mut folder = SevenZArchiveEntry::new();
+        folder.name = "mapset".to_string();
+        folder.is_directory = true;
+        writer.push_archive_entry::<&[u8]>(folder, None).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "imp-porter-archive-mapset-{}.7z",
+            std::process::id()
+        ));
+        fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+
+        assert_eq!(ArchiveFormat::sniff(&path), Some(ArchiveFormat::SevenZip));
+        // the folder entry is left out
+        assert_eq!(
+            read_files(&path).unwrap(),
+            vec![
+                ("mapset/maps.wad".to_string(), "PWAD".to_string()),
+                (
+                    "mapset/mapset.txt".to_string(),
+                    "Title : Mapset".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn has_no_rar_backend() {
+        assert!(ArchiveFormat::Rar.backend().is_err());
+    }
+}
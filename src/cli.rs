@@ -0,0 +1,546 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::compat::CompatLevel;
+use crate::config_functions::{self, Config};
+use crate::library::Library;
+use crate::query::{ModQuery, SortKey};
+use crate::saves::Owner;
+use crate::source_port::SourcePort;
+use crate::utility::ModKind;
+use crate::{commands, import, profiles, saves, utility, wad};
+
+/// Command line options for imp-orter. Without a subcommand the Slint UI is started.
+#[derive(Debug, Parser)]
+#[command(
+    name = "imp-porter",
+    version,
+    about = "A WAD manager and launcher for Doom"
+)]
+pub struct Cli {
+    /// Use this config file instead of the default one
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Keep the config, data and cache next to the executable
+    #[arg(long, global = true)]
+    pub portable: bool,
+
+    /// Print results as JSON for scripts
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+/// The headless subcommands
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// List the mods in the mods directory
+    List {
+        /// Only list mods with this text in their file name, folder, title or author
+        #[arg(long)]
+        search: Option<String>,
+        /// Only list mods of this kind (wad, pk3, pk7, ipk3, deh, bex)
+        #[arg(long, value_parser = parse_kind)]
+        kind: Option<ModKind>,
+        /// Only list mods for this IWAD file name, e.g. doom2.wad
+        #[arg(long)]
+        iwad: Option<String>,
+        /// Only list mods with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only list mods of this compatibility level (vanilla, boom, mbf, mbf21, zdoom)
+        #[arg(long, value_parser = parse_compat)]
+        compat: Option<CompatLevel>,
+        /// Order of the list: name, date-added, last-played, size or map-count
+        #[arg(long, value_parser = parse_sort, default_value = "name")]
+        sort: SortKey,
+    },
+    /// Import an archive, a mod file or a folder into the mods directory
+    Import {
+        /// The archive (zip, 7z, tar.gz, tar.xz, tar.bz2), mod file or folder to import
+        path: PathBuf,
+    },
+    /// Launch a source port with mods, in load order
+    Play {
+        /// Mods to load, by file name in the mods directory or by path.
+        /// With --preset they are loaded after the preset's mods
+        mods: Vec<String>,
+        /// Saved preset to launch. The other options replace the preset's values
+        #[arg(long)]
+        preset: Option<String>,
+        /// IWAD to use, by path or by file name of a registered IWAD
+        #[arg(long)]
+        iwad: Option<String>,
+        /// Source port to use, by path or by name (gzdoom, dsda-doom, ...)
+        #[arg(long)]
+        port: Option<String>,
+        /// Map to start on (MAP01, E1M1 or a custom map name)
+        #[arg(long)]
+        warp: Option<String>,
+        /// Skill level from 1 to 5
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=5))]
+        skill: Option<u8>,
+    },
+    /// List the saves kept for each mod and preset
+    Saves,
+    /// Export the tags, ratings, notes, favorites and status of every mod as JSON
+    Export {
+        /// File to write to, printed when not given
+        file: Option<PathBuf>,
+    },
+    /// Show or change settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+/// Actions of the config subcommand
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the current config
+    Show,
+    /// Change a setting: source_port, wad_path, mods_directory, import.max_depth,
+    /// import.max_total_size (bytes) or import.max_entries
+    Set { key: String, value: String },
+}
+
+/// Function to run a headless subcommand
+///
+/// #Arguments
+/// - the subcommand to run
+/// - true to print results as JSON
+///
+/// #Returns
+/// - the process exit code, 0 on success
+pub fn run(command: CliCommand, json: bool) -> i32 {
+    let result = match command {
+        CliCommand::List {
+            search,
+            kind,
+            iwad,
+            tag,
+            compat,
+            sort,
+        } => list_mods(
+            &ModQuery {
+                search: search.unwrap_or_default(),
+                kind,
+                iwad,
+                tag,
+                compat,
+                sort,
+            },
+            json,
+        ),
+        CliCommand::Import { path } => import_path(&path, json),
+        CliCommand::Play {
+            mods,
+            preset,
+            iwad,
+            port,
+            warp,
+            skill,
+        } => match preset {
+            Some(name) => play_preset(&name, &mods, iwad, port, warp, skill, json),
+            None => play(&mods, iwad, port, warp, skill, json),
+        },
+        CliCommand::Saves => list_saves(json),
+        CliCommand::Export { file } => export_user_data(file.as_deref()),
+        CliCommand::Config { action } => match action {
+            ConfigAction::Show => show_config(json),
+            ConfigAction::Set { key, value } => set_config(&key, &value, json),
+        },
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            if json {
+                println!("{}", serde_json::json!({ "error": e }));
+            } else {
+                eprintln!("Error: {}", e);
+            }
+            1
+        }
+    }
+}
+
+/// Prints the mods in the mods directory and its subfolders that match a query, in the
+/// query's order
+fn list_mods(query: &ModQuery, json: bool) -> Result<(), String> {
+    let config = config_functions::load_config();
+    if config.mods_directory.is_empty() {
+        return Err("no mods directory set, use `config set mods_directory <path>`".to_string());
+    }
+
+    let mods = query.run(
+        &Library::open()?.scan(&config.mods_directory)?,
+        &config.mods,
+    );
+
+    if json {
+        println!("{}", to_json(&mods)?);
+    } else {
+        for listed in &mods {
+            println!(
+                "{}\t{}\t{}",
+                utility::relative_path(&config.mods_directory, Path::new(&listed.entry.path)),
+                listed.entry.kind.as_str(),
+                wad::describe_maps(&listed.entry.maps)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Imports an archive, mod file or folder into the mods directory and prints what happened to each file
+fn import_path(path: &Path, json: bool) -> Result<(), String> {
+    let config = config_functions::load_config();
+    if config.mods_directory.is_empty() {
+        return Err("no mods directory set, use `config set mods_directory <path>`".to_string());
+    }
+
+    let report = import::import_path(path, &config.mods_directory, &config.import);
+    if report.error.is_some() {
+        return Err(report.summary());
+    }
+
+    if json {
+        println!("{}", to_json(&report)?);
+    } else {
+        println!("{}", report.summary());
+    }
+    Ok(())
+}
+
+/// Launches a source port with the given mods
+///
+/// Without --iwad or --port they are picked as in the Play view: the ones the first mod
+/// was last played with, then the IWAD its GAMEINFO asks for, then the defaults.
+fn play(
+    mods: &[String],
+    iwad: Option<String>,
+    port: Option<String>,
+    warp: Option<String>,
+    skill: Option<u8>,
+    json: bool,
+) -> Result<(), String> {
+    let config = config_functions::load_config();
+
+    let mod_paths = mods
+        .iter()
+        .map(|name| resolve_mod(&config, name))
+        .collect::<Result<Vec<String>, String>>()?;
+    // the settings, saves and config profile belong to the first mod, as in the Play view
+    let first = mod_paths
+        .first()
+        .map(|path| utility::relative_path(&config.mods_directory, Path::new(path)));
+    let remembered = first
+        .as_ref()
+        .and_then(|key| config.mods.get(key))
+        .cloned()
+        .unwrap_or_default();
+
+    let iwad = iwad
+        .or(remembered.iwad.filter(|path| Path::new(path).is_file()))
+        .or_else(|| required_iwad(&config, mod_paths.first()?));
+    let iwad_path = resolve_iwad(&config, iwad)?;
+    let port_path = resolve_port(
+        &config,
+        port.or(remembered.port.filter(|path| Path::new(path).is_file())),
+    )?;
+
+    let save_dir = first.as_deref().map(saves::mod_save_dir);
+    let config_file = first.as_deref().and_then(|first| {
+        SourcePort::from_executable(&port_path)
+            .and_then(|port| profiles::profile_for(Owner::Mod, first, port))
+    });
+    commands::launch_game_with_mods(
+        &port_path,
+        &iwad_path,
+        &mod_paths,
+        warp.as_deref(),
+        skill,
+        save_dir.as_deref(),
+        config_file.as_deref(),
+    )
+    .map_err(|e| format!("failed to launch: {}", e))?;
+
+    if let Err(e) = Library::open().and_then(|mut library| library.record_play(&mod_paths)) {
+        eprintln!("Failed to count the launch in the library: {}", e);
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "port": port_path, "iwad": iwad_path, "mods": mod_paths })
+        );
+    } else {
+        println!("Launched {}", port_path);
+    }
+    Ok(())
+}
+
+/// Launches a saved preset, with the options given on the command line taking its place
+fn play_preset(
+    name: &str,
+    mods: &[String],
+    iwad: Option<String>,
+    port: Option<String>,
+    warp: Option<String>,
+    skill: Option<u8>,
+    json: bool,
+) -> Result<(), String> {
+    let config = config_functions::load_config();
+    let mut preset = config.preset(name).cloned().ok_or_else(|| {
+        let names: Vec<&str> = config.presets.iter().map(|p| p.name.as_str()).collect();
+        format!(
+            "unknown preset '{}', saved presets: {}",
+            name,
+            names.join(", ")
+        )
+    })?;
+
+    let iwad_path = resolve_iwad(&config, iwad.or(preset.iwad.take()))?;
+    let port_path = resolve_port(&config, port.or(preset.port.take()))?;
+    preset.mods = preset
+        .mods
+        .iter()
+        .chain(mods)
+        .map(|name| resolve_mod(&config, name))
+        .collect::<Result<Vec<String>, String>>()?;
+    preset.warp = warp.or(preset.warp);
+    preset.skill = skill.or(preset.skill);
+
+    let save_dir = saves::preset_save_dir(&preset.name);
+    let config_file = SourcePort::from_executable(&port_path)
+        .and_then(|port| profiles::profile_for(Owner::Preset, &preset.name, port));
+    commands::launch_preset(
+        &port_path,
+        &iwad_path,
+        &preset,
+        Some(&save_dir),
+        config_file.as_deref(),
+    )
+    .map_err(|e| format!("failed to launch: {}", e))?;
+
+    if let Err(e) = Library::open().and_then(|mut library| library.record_play(&preset.mods)) {
+        eprintln!("Failed to count the launch in the library: {}", e);
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "preset": preset.name,
+                "port": port_path,
+                "iwad": iwad_path,
+                "mods": preset.mods,
+                "args": preset.args,
+            })
+        );
+    } else {
+        println!("Launched {} with {}", preset.name, port_path);
+    }
+    Ok(())
+}
+
+/// Prints the saves of every mod and preset, newest first
+fn list_saves(json: bool) -> Result<(), String> {
+    let folders = saves::list_saves();
+    if json {
+        println!("{}", to_json(&folders)?);
+        return Ok(());
+    }
+
+    for folder in &folders {
+        println!("{} {}", folder.owner.as_str(), folder.name);
+        for save in &folder.saves {
+            println!(
+                "\t{}\t{}\t{} bytes",
+                save.modified_label(),
+                save.name,
+                save.size
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Writes the user data of the library as JSON to a file or stdout
+fn export_user_data(file: Option<&Path>) -> Result<(), String> {
+    let contents = to_json(&Library::open()?.export_user_data()?)?;
+    match file {
+        Some(file) => std::fs::write(file, contents)
+            .map_err(|e| format!("failed to write {}: {}", file.display(), e)),
+        None => {
+            println!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+/// Prints the current config as TOML, or JSON with --json
+fn show_config(json: bool) -> Result<(), String> {
+    let config = config_functions::load_config();
+    if json {
+        println!("{}", to_json(&config)?);
+    } else {
+        print!(
+            "{}",
+            toml::to_string_pretty(&config).map_err(|e| e.to_string())?
+        );
+    }
+    Ok(())
+}
+
+/// Changes a single setting
+fn set_config(key: &str, value: &str, json: bool) -> Result<(), String> {
+    let mut config = config_functions::load_config();
+    match key {
+        "source_port" => {
+            if SourcePort::from_executable(value).is_none() {
+                return Err(format!("'{}' is not a supported source port", value));
+            }
+            config.source_port = value.to_string();
+        }
+        "wad_path" => config.wad_path = value.to_string(),
+        "mods_directory" => config.mods_directory = value.to_string(),
+        "import.max_depth" => config.import.max_depth = parse_number(key, value)?,
+        "import.max_total_size" => config.import.max_total_size = parse_number(key, value)?,
+        "import.max_entries" => config.import.max_entries = parse_number(key, value)?,
+        _ => {
+            return Err(format!(
+                "unknown key '{}', expected source_port, wad_path, mods_directory, \
+                 import.max_depth, import.max_total_size or import.max_entries",
+                key
+            ))
+        }
+    }
+
+    config_functions::save_config(&config).map_err(|e| e.to_string())?;
+
+    if json {
+        println!("{}", serde_json::json!({ key: value }));
+    } else {
+        println!("{} = {}", key, value);
+    }
+    Ok(())
+}
+
+/// Parses the number given for a numeric setting
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a whole number, got '{}'", key, value))
+}
+
+/// Finds the IWAD to play with: a path, the file name of a registered IWAD, or the default
+fn resolve_iwad(config: &Config, iwad: Option<String>) -> Result<String, String> {
+    let Some(iwad) = iwad else {
+        if config.wad_path.is_empty() {
+            return Err("no IWAD set, use --iwad or `config set wad_path <path>`".to_string());
+        }
+        return Ok(config.wad_path.clone());
+    };
+
+    if Path::new(&iwad).is_file() {
+        return Ok(iwad);
+    }
+
+    std::iter::once(&config.wad_path)
+        .chain(config.iwads.iter())
+        .find(|path| {
+            Path::new(path)
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(&iwad))
+        })
+        .cloned()
+        .ok_or_else(|| format!("unknown IWAD '{}'", iwad))
+}
+
+/// Finds the registered IWAD a mod's GAMEINFO asks for, if it has one
+fn required_iwad(config: &Config, mod_path: &str) -> Option<String> {
+    let kind = ModKind::from_path(mod_path)?;
+    let required = utility::read_mod_entry(mod_path, kind)
+        .ok()?
+        .required_iwad?;
+    resolve_iwad(config, Some(required)).ok()
+}
+
+/// Finds the source port to launch: a path, the name of a registered port, or the default
+fn resolve_port(config: &Config, port: Option<String>) -> Result<String, String> {
+    let Some(port) = port else {
+        if config.source_port.is_empty() {
+            return Err(
+                "no source port set, use --port or `config set source_port <path>`".to_string(),
+            );
+        }
+        return Ok(config.source_port.clone());
+    };
+
+    if Path::new(&port).is_file() {
+        return Ok(port);
+    }
+
+    std::iter::once(&config.source_port)
+        .chain(config.ports.iter())
+        .find(|path| {
+            SourcePort::from_executable(path).is_some_and(|known| {
+                known.name().eq_ignore_ascii_case(&port)
+                    || Path::new(path)
+                        .file_stem()
+                        .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(&port))
+            })
+        })
+        .cloned()
+        .ok_or_else(|| format!("unknown source port '{}'", port))
+}
+
+/// Finds a mod by path, by path relative to the mods directory, or by file name in the
+/// mods directory and its subfolders
+fn resolve_mod(config: &Config, name: &str) -> Result<String, String> {
+    if Path::new(name).is_file() {
+        return Ok(name.to_string());
+    }
+
+    let in_library = Path::new(&config.mods_directory).join(name);
+    if !config.mods_directory.is_empty() && in_library.is_file() {
+        return Ok(in_library.to_string_lossy().into_owned());
+    }
+
+    // a bare file name also finds mods kept in subfolders
+    if let Some(found) = utility::find_mod_files(&config.mods_directory)
+        .into_iter()
+        .find(|path| path.file_name().is_some_and(|file_name| file_name == name))
+    {
+        return Ok(found.to_string_lossy().into_owned());
+    }
+
+    Err(format!("mod '{}' not found", name))
+}
+
+/// Reads the --kind value of `list`
+fn parse_kind(value: &str) -> Result<ModKind, String> {
+    ModKind::from_label(value).ok_or_else(|| format!("unknown kind '{}'", value))
+}
+
+/// Reads the --compat value of `list`
+fn parse_compat(value: &str) -> Result<CompatLevel, String> {
+    CompatLevel::from_label(value).ok_or_else(|| format!("unknown compatibility level '{}'", value))
+}
+
+/// Reads the --sort value of `list`
+fn parse_sort(value: &str) -> Result<SortKey, String> {
+    SortKey::from_label(value).ok_or_else(|| format!("unknown sort order '{}'", value))
+}
+
+/// Serializes a value as pretty printed JSON
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
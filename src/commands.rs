@@ -1,93 +1,139 @@
-use rfd::FileDialog;
 use std::fs;
-use std::fs::File;
-use std::io::BufReader;
-use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Command;
-use zip::read::ZipArchive;
 
-///Function to build a command to execute the GZDoom Launcher
+use crate::config_functions::Preset;
+use crate::source_port::SourcePort;
+
+///Function to build a command to execute a source port with no mods
 ///
 /// #Arguments
-/// - path to gzdoom launcher as a string reference
-/// - path to the doom II wad as a string reference
+/// - path to the source port executable as a string reference
+/// - path to the IWAD as a string reference
 ///
 /// #Returns
-/// - Error messages if paths are not defined
-/// - Executes the application and begins to run GZDoom
+/// - Error messages if paths are not defined or the executable is not a supported port
+/// - Executes the application and begins to run the game
 ///
 /// Imp-orter continues to run while playing
-pub fn launch_gzdoom(gzdoom_path: &str, wad_path: &str) -> std::io::Result<()> {
-    if !Path::new(gzdoom_path).exists() {
-        eprintln!("Error: GZDoom executable not found at '{}'", gzdoom_path);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "GZDoom not found",
-        ));
-    }
+pub fn launch_game(port_path: &str, wad_path: &str) -> std::io::Result<()> {
+    let port = check_port(port_path)?;
+    check_wad(wad_path)?;
 
-    // check path existences
-    if !Path::new(wad_path).exists() {
-        eprintln!("Error: WAD file not found at '{}'", wad_path);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "WAD file not found",
-        ));
-    }
+    let args = port
+        .build_args(wad_path, &[], None, None)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
 
-    let mut command = if cfg!(target_os = "macos") {
-        // macOS: Use "open -a" to launch GZDoom
-        let mut cmd = Command::new("open");
-        cmd.arg("-a")
-            .arg(gzdoom_path)
-            .arg("--args")
-            .arg("-iwad")
-            .arg(wad_path);
-        cmd
-    } else if cfg!(target_os = "windows") {
-        // Windows: execute the .exe file
-        let mut cmd = Command::new(gzdoom_path);
-        cmd.arg("-iwad").arg(wad_path);
-        cmd
-    } else {
-        // Linux: execute the binary
-        let mut cmd = Command::new(gzdoom_path);
-        cmd.arg("-iwad").arg(wad_path);
-        cmd
-    };
-
-    command.spawn()?; // Spawn without waiting
-    Ok(())
+    spawn_port(port_path, &args)
 }
 
-///Function to build a command to execute the GZDoom Launcher with a chosen mod
+///Function to build a command to execute a source port with a list of mods
 ///
 /// #Arguments
-/// - path to gzdoom launcher as a string reference
-/// - path to the doom II wad as a string reference
-/// - path to a selected wad from the mod file directory
+/// - path to the source port executable as a string reference
+/// - path to the IWAD as a string reference
+/// - paths to the mod files in the order they should be loaded, empty to play the IWAD alone
+/// - optional map to start on (MAPxx, ExMy or a custom map name)
+/// - optional skill level from 1 to 5
+/// - optional folder to keep the saves in, created if needed
+/// - optional config file to use instead of the port's own, see the profiles module
 ///
 /// #Returns
-/// - Error messages if paths are not defined
-/// - Executes the application and begins to run GZDoom
+/// - Error messages if paths are not defined, or if the port can't load a file or map
+/// - Executes the application and begins to run the game
 ///
+/// The arguments follow the port's own conventions (see SourcePort::build_args).
 /// Imp-orter continues to run while playing.
-pub fn launch_gzdoom_with_mod(
-    gzdoom_path: &str,
+pub fn launch_game_with_mods(
+    port_path: &str,
+    wad_path: &str,
+    mod_paths: &[String],
+    start_map: Option<&str>,
+    skill: Option<u8>,
+    save_dir: Option<&Path>,
+    config_file: Option<&Path>,
+) -> std::io::Result<()> {
+    let port = check_port(port_path)?;
+    check_wad(wad_path)?;
+
+    check_mods(mod_paths)?;
+
+    let mut args = port
+        .build_args(wad_path, mod_paths, start_map, skill)
+        .map_err(|e| {
+            eprintln!("Error: {}", e);
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+        })?;
+    args.extend(save_args(port, save_dir)?);
+    args.extend(
+        config_file
+            .map(|file| port.config_args(file))
+            .unwrap_or_default(),
+    );
+
+    spawn_port(port_path, &args)
+}
+
+///Function to build a command to execute a source port with a saved preset
+///
+/// #Arguments
+/// - path to the source port executable as a string reference
+/// - path to the IWAD as a string reference
+/// - the Preset with the mods, start map, skill and extra arguments
+/// - optional folder to keep the saves in, created if needed
+/// - optional config file to use instead of the port's own, see the profiles module
+///
+/// #Returns
+/// - Error messages if paths are not defined, or if the port can't load a file or map
+/// - Executes the application and begins to run the game
+///
+/// Unlike launch_game_with_mods a preset may have no mods. The extra arguments come last.
+pub fn launch_preset(
+    port_path: &str,
     wad_path: &str,
-    mod_path: &str,
+    preset: &Preset,
+    save_dir: Option<&Path>,
+    config_file: Option<&Path>,
 ) -> std::io::Result<()> {
+    let port = check_port(port_path)?;
+    check_wad(wad_path)?;
+    check_mods(&preset.mods)?;
+
+    let mut args = port
+        .build_args(wad_path, &preset.mods, preset.warp.as_deref(), preset.skill)
+        .map_err(|e| {
+            eprintln!("Error: {}", e);
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+        })?;
+    args.extend(save_args(port, save_dir)?);
+    args.extend(
+        config_file
+            .map(|file| port.config_args(file))
+            .unwrap_or_default(),
+    );
+    args.extend(preset.args.iter().cloned());
+
+    spawn_port(port_path, &args)
+}
 
-    // check path existences
-    if !Path::new(gzdoom_path).exists() {
-        eprintln!("Error: GZDoom executable not found at '{}'", gzdoom_path);
+/// Checks that the source port exists and is one imp-orter knows how to launch
+fn check_port(port_path: &str) -> std::io::Result<SourcePort> {
+    if !Path::new(port_path).exists() {
+        eprintln!("Error: Source port executable not found at '{}'", port_path);
         return Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
-            "GZDoom not found",
+            "Source port not found",
         ));
     }
 
+    SourcePort::from_executable(port_path).ok_or_else(|| {
+        eprintln!("Error: '{}' is not a supported source port", port_path);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "Unsupported source port")
+    })
+}
+
+/// Checks that the IWAD exists
+fn check_wad(wad_path: &str) -> std::io::Result<()> {
     if !Path::new(wad_path).exists() {
         eprintln!("Error: WAD file not found at '{}'", wad_path);
         return Err(std::io::Error::new(
@@ -95,113 +141,65 @@ pub fn launch_gzdoom_with_mod(
             "WAD file not found",
         ));
     }
+    Ok(())
+}
 
-    if !Path::new(mod_path).exists() {
-        eprintln!("Error: Mod file not found at '{}'", mod_path);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Mod file not found",
-        ));
+/// Checks that every mod file exists
+fn check_mods(mod_paths: &[String]) -> std::io::Result<()> {
+    for mod_path in mod_paths {
+        if !Path::new(mod_path).exists() {
+            eprintln!("Error: Mod file not found at '{}'", mod_path);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Mod file not found",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Builds the arguments to keep saves in a folder, creating the folder first.
+/// Ports that can't move their saves keep using their own save folder.
+fn save_args(port: SourcePort, save_dir: Option<&Path>) -> std::io::Result<Vec<String>> {
+    let Some(dir) = save_dir else {
+        return Ok(Vec::new());
+    };
+
+    match port.save_dir_args(dir) {
+        Some(args) => {
+            fs::create_dir_all(dir)?;
+            Ok(args)
+        }
+        None => {
+            eprintln!(
+                "{} can't keep saves in {}, using its own save folder",
+                port.name(),
+                dir.display()
+            );
+            Ok(Vec::new())
+        }
     }
+}
 
+/// Spawns the source port with its arguments without waiting for it to exit
+fn spawn_port(port_path: &str, args: &[String]) -> std::io::Result<()> {
     let mut command = if cfg!(target_os = "macos") {
-        // macOS: Use "open -a" to launch GZDoom
+        // macOS: Use "open -a" to launch the app bundle
         let mut cmd = Command::new("open");
-        cmd.arg("-a")
-            .arg(gzdoom_path)
-            .arg("--args")
-            .arg("-iwad")
-            .arg(wad_path)
-            .arg("-file")
-            .arg(mod_path);
+        cmd.arg("-a").arg(port_path).arg("--args").args(args);
         cmd
     } else if cfg!(target_os = "windows") {
         // Windows: execute the .exe file
-        let mut cmd = Command::new(gzdoom_path);
-        cmd.arg("-iwad").arg(wad_path).arg("-file").arg(mod_path);
+        let mut cmd = Command::new(port_path);
+        cmd.args(args);
         cmd
     } else {
         // Linux: execute the binary
-        let mut cmd = Command::new(gzdoom_path);
-        cmd.arg("-iwad").arg(wad_path).arg("-file").arg(mod_path);
+        let mut cmd = Command::new(port_path);
+        cmd.args(args);
         cmd
     };
 
-    command.spawn()?;
-    Ok(())
-}
-
-///Function to select a zip file and extract a wad from it and move the file to the mods directory
-///
-/// #Arguments
-/// - path to the mods directory
-///
-/// #Returns
-/// - Error messages if no zip is selected, no wad file exists, and if it already exists in mod directory
-/// - extracted .wad file is moved to the mods directory
-pub fn extract_and_move_wad(target_dir: &str) -> std::io::Result<()> {
-    // Open file dialog for ZIP selection
-    let zip_path = FileDialog::new()
-        .add_filter("ZIP Files", &["zip"]) // Allow only .zip files
-        .pick_file();
-
-    let zip_path = match zip_path {
-        Some(path) => path,
-        None => {
-            println!("No ZIP file selected.");
-            return Ok(()); // Exit if no file was chosen
-        }
-    };
-
-    println!("Selected ZIP file: {:?}", zip_path);
-
-    // Open the ZIP file
-    let file = File::open(&zip_path)?;
-    let mut archive = ZipArchive::new(BufReader::new(file))?;
-
-    let mut extracted_wad = false; // Track if we extracted a file
-
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let file_name = file.name();
-
-        // Skip directories inside ZIP
-        if file_name.ends_with('/') {
-            continue;
-        }
-
-        // Extract only .wad files
-        if file_name.to_lowercase().ends_with(".wad") {
-            let file_stem = Path::new(file_name)
-                .file_name() // Extract filename without directories
-                .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "Invalid file name"))?
-                .to_string_lossy()
-                .to_string();
-
-            let final_path = Path::new(target_dir).join(&file_stem);
-
-            // Check if file already exists
-            if final_path.exists() {
-                println!("File {} already exists in {}", file_stem, target_dir);
-                continue; // Check for other WAD files instead of returning immediately
-            }
-
-            println!("Extracting: {}", file_stem);
-            let mut outfile = File::create(&final_path)?;
-            std::io::copy(&mut file, &mut outfile)?;
-
-            // Set file permissions on Unix-based systems
-            #[cfg(target_family = "unix")]
-            fs::set_permissions(&final_path, fs::Permissions::from_mode(0o644))?;
-
-            println!("Extracted {} to {}", file_stem, final_path.display());
-            extracted_wad = true;
-        }
-    }
-
-    if !extracted_wad {
-        println!("No .wad file found in the ZIP.");
-    }
-
+    command.spawn()?; // Spawn without waiting
     Ok(())
 }
@@ -0,0 +1,172 @@
+use serde::Serialize;
+
+use crate::utility::ModKind;
+use crate::wad::Lump;
+
+/// The feature set a mod needs from the source port, from plain Doom to the ZDoom family.
+/// Ports that support a level also run every level before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompatLevel {
+    Vanilla,
+    Boom,
+    Mbf,
+    Mbf21,
+    Zdoom,
+}
+
+/// Lumps that only the ZDoom family reads. TEXTMAP is a UDMF map and BEHAVIOR a Hexen format map.
+const ZDOOM_LUMPS: [&str; 11] = [
+    "MAPINFO", "ZMAPINFO", "DECORATE", "ZSCRIPT", "GLDEFS", "KEYCONF", "TEXTURES", "LANGUAGE",
+    "SNDINFO", "TEXTMAP", "BEHAVIOR",
+];
+
+/// Lumps introduced by Boom, or by standards that need at least a Boom port. Vanilla only
+/// reads DeHackEd patches given on the command line, Boom also loads a DEHACKED lump.
+const BOOM_LUMPS: [&str; 4] = ["SWITCHES", "ANIMATED", "UMAPINFO", "DEHACKED"];
+
+impl CompatLevel {
+    /// Every level, in order
+    pub const ALL: [CompatLevel; 5] = [
+        CompatLevel::Vanilla,
+        CompatLevel::Boom,
+        CompatLevel::Mbf,
+        CompatLevel::Mbf21,
+        CompatLevel::Zdoom,
+    ];
+
+    /// Short lowercase label used in the UI and the library
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompatLevel::Vanilla => "vanilla",
+            CompatLevel::Boom => "boom",
+            CompatLevel::Mbf => "mbf",
+            CompatLevel::Mbf21 => "mbf21",
+            CompatLevel::Zdoom => "zdoom",
+        }
+    }
+
+    /// Function to read a level from its label, as written in a COMPLVL lump
+    ///
+    /// #Arguments
+    /// - the label, case-insensitive
+    ///
+    /// #Returns
+    /// - the CompatLevel, or None if the label is unknown
+    pub fn from_label(label: &str) -> Option<CompatLevel> {
+        let label = label.trim().to_lowercase();
+        CompatLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str() == label)
+    }
+}
+
+/// Function to work out the compatibility level a mod file needs
+///
+/// #Arguments
+/// - the kind of mod file
+/// - the lump directory of a WAD, empty for other kinds
+/// - contents of the WAD's COMPLVL lump, if it has one
+///
+/// #Returns
+/// - the CompatLevel
+///
+/// A COMPLVL lump states the level and is trusted. Otherwise the level is guessed from
+/// lumps only some ports read. pk3, pk7 and ipk3 files are ZDoom only and .bex patches
+/// use Boom's DeHackEd extensions.
+pub fn detect_compat(kind: ModKind, lumps: &[Lump], complvl: Option<&str>) -> CompatLevel {
+    match kind {
+        ModKind::Pk3 | ModKind::Pk7 | ModKind::Ipk3 => return CompatLevel::Zdoom,
+        ModKind::Bex => return CompatLevel::Boom,
+        ModKind::Deh => return CompatLevel::Vanilla,
+        ModKind::Wad => {}
+    }
+
+    if let Some(level) = complvl.and_then(CompatLevel::from_label) {
+        return level;
+    }
+
+    let has_any = |names: &[&str]| {
+        lumps.iter().any(|lump| {
+            names
+                .iter()
+                .any(|name| lump.name.eq_ignore_ascii_case(name))
+        })
+    };
+
+    if has_any(&ZDOOM_LUMPS) {
+        CompatLevel::Zdoom
+    } else if has_any(&BOOM_LUMPS) {
+        CompatLevel::Boom
+    } else {
+        CompatLevel::Vanilla
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lumps(names: &[&str]) -> Vec<Lump> {
+        names
+            .iter()
+            .map(|name| Lump {
+                name: name.to_string(),
+                offset: 12,
+                size: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn maps_and_graphics_are_vanilla() {
+        let lumps = lumps(&[
+            "MAP01", "THINGS", "LINEDEFS", "SIDEDEFS", "PLAYPAL", "D_RUNNIN",
+        ]);
+        assert_eq!(
+            detect_compat(ModKind::Wad, &lumps, None),
+            CompatLevel::Vanilla
+        );
+        assert_eq!(detect_compat(ModKind::Deh, &[], None), CompatLevel::Vanilla);
+    }
+
+    #[test]
+    fn dehacked_and_umapinfo_lumps_need_boom() {
+        for name in ["DEHACKED", "umapinfo"] {
+            assert_eq!(
+                detect_compat(ModKind::Wad, &lumps(&["MAP01", name]), None),
+                CompatLevel::Boom,
+                "{}",
+                name
+            );
+        }
+        assert_eq!(detect_compat(ModKind::Bex, &[], None), CompatLevel::Boom);
+    }
+
+    #[test]
+    fn trusts_the_complvl_lump() {
+        let lumps = lumps(&["MAP01", "COMPLVL", "DEHACKED"]);
+        assert_eq!(
+            detect_compat(ModKind::Wad, &lumps, Some("MBF21\n")),
+            CompatLevel::Mbf21
+        );
+        // an unknown level falls back to guessing from the lumps
+        assert_eq!(
+            detect_compat(ModKind::Wad, &lumps, Some("doom 9")),
+            CompatLevel::Boom
+        );
+    }
+
+    #[test]
+    fn zdoom_lumps_and_archives_need_zdoom() {
+        assert_eq!(
+            detect_compat(
+                ModKind::Wad,
+                &lumps(&["ZMAPINFO", "UMAPINFO", "MAP01"]),
+                None
+            ),
+            CompatLevel::Zdoom
+        );
+        assert_eq!(detect_compat(ModKind::Pk3, &[], None), CompatLevel::Zdoom);
+    }
+}
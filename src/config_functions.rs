@@ -1,168 +1,420 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-/// Function to Check Project Directory for documents/config.txt
-///
-/// #Arguments
-///  - none
-///
-/// #Returns
-/// - PathBuf
-///
-/// #To-do
-/// - Figure out how to not rely on this when packaging for a release.
-pub fn get_config_path() -> PathBuf {
-    let project_root = std::env::current_dir().expect("Failed to get current directory");
-    project_root.join("documents/config.txt")
+use crate::paths;
+
+/// Version of the config schema written by this build. Bump it whenever a field is
+/// renamed or changes meaning and add the upgrade step to `migrate_config`.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Settings imp-orter remembers for a single mod file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModSettings {
+    /// IWAD the mod was last played with
+    pub iwad: Option<String>,
+    /// Source port the mod was last played with
+    pub port: Option<String>,
+}
+
+/// A named launch configuration, stored as a [[presets]] table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    /// IWAD to launch, the default IWAD when not set
+    pub iwad: Option<String>,
+    /// Source port to launch, the default port when not set
+    pub port: Option<String>,
+    /// Paths of the mod files in the order they are loaded
+    pub mods: Vec<String>,
+    /// Map to start on
+    pub warp: Option<String>,
+    /// Skill level from 1 to 5
+    pub skill: Option<u8>,
+    /// Extra arguments passed to the port after everything else, e.g. ["-fast"]
+    pub args: Vec<String>,
+}
+
+/// Limits applied when importing archives, stored in the [import] table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    /// How many levels of archives inside archives are opened
+    pub max_depth: u32,
+    /// Most bytes a single import may write, to stop zip bombs
+    pub max_total_size: u64,
+    /// Most files a single import may read from its archives
+    pub max_entries: u64,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        ImportSettings {
+            max_depth: 3,
+            max_total_size: 4 * 1024 * 1024 * 1024,
+            max_entries: 10_000,
+        }
+    }
 }
 
-/// Function to read the config.txt files and extract the parameters
+/// Everything stored in config.toml
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Schema version the file was written with
+    pub version: u32,
+    /// Default source port executable
+    pub source_port: String,
+    /// Default IWAD
+    pub wad_path: String,
+    /// Folder where mod files are kept
+    pub mods_directory: String,
+    /// Additional IWADs the user can choose from when launching
+    pub iwads: Vec<String>,
+    /// Additional source ports the user can choose from when launching
+    pub ports: Vec<String>,
+    /// Per-mod settings keyed by the mod's path relative to the mods directory,
+    /// e.g. "maps/megawads/scythe.wad"
+    pub mods: BTreeMap<String, ModSettings>,
+    /// Limits for importing archives
+    pub import: ImportSettings,
+    /// Saved launch configurations, in the order they were created
+    pub presets: Vec<Preset>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            source_port: String::new(),
+            wad_path: String::new(),
+            mods_directory: String::new(),
+            iwads: Vec::new(),
+            ports: Vec::new(),
+            mods: BTreeMap::new(),
+            import: ImportSettings::default(),
+            presets: Vec::new(),
+        }
+    }
+}
+
+/// Function to get the path of config.toml
 ///
 /// #Arguments
-///  - path to file as a string reference
+///  - none
 ///
 /// #Returns
-/// - Hashmap of the parameters that were stored
-pub fn read_config(_file_path: &str) -> HashMap<String, String> {
-    let content = check_config_file(); // Ensure config file exists before reading
-
-    content
-        .lines()
-        .filter_map(|line| {
-            let mut parts = line.splitn(2, '=');
-            Some((
-                parts.next()?.trim().to_string(),
-                parts.next()?.trim().to_string(),
-            ))
-        })
-        .collect()
+/// - PathBuf, resolved by the paths module ($XDG_CONFIG_HOME/imp-porter/config.toml,
+///   the --config override, or next to the executable in portable mode)
+pub fn get_config_path() -> PathBuf {
+    paths::get().config_file.clone()
 }
 
-/// Checks the contents of config file and creates a new config.txt if one does not exist
+/// Function to load the config, creating or migrating it when needed
 ///
 /// #Arguments
-/// - none
+///  - none
 ///
 /// #Returns
-/// - String of the config.txt file
-pub fn check_config_file() -> String {
+/// - the Config stored in config.toml
+/// - if there is no config.toml, one left in the working directory's documents/ folder by an
+///   older version is moved over, or an old config.txt is migrated. Otherwise a default config
+///   is created
+/// - if config.toml can't be parsed, the error is reported, a copy is kept as config.toml.bak
+///   and the defaults are used. save_config refuses to overwrite the file until it is fixed
+pub fn load_config() -> Config {
     let config_path = get_config_path();
 
-    // If the config file exists, read and return its content
     if config_path.exists() {
-        let mut file = File::open(&config_path).expect("Failed to open config file");
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .expect("Failed to read config file");
-        return contents;
+        return match read_config(&config_path) {
+            Ok(config) => migrate_config(config),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", config_path.display(), e);
+                let backup = config_path.with_extension("toml.bak");
+                if let Err(e) = fs::copy(&config_path, &backup) {
+                    eprintln!("Failed to back up {}: {}", config_path.display(), e);
+                }
+                Config::default()
+            }
+        };
     }
 
-    // Ensure the parent directory exists
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent).expect("Failed to create documents directory");
-    }
+    let legacy_toml_path = paths::legacy_documents_dir().join("config.toml");
+    let legacy_path = paths::legacy_documents_dir().join("config.txt");
+    let config = if legacy_toml_path.exists() {
+        eprintln!(
+            "Moving {} to {}",
+            legacy_toml_path.display(),
+            config_path.display()
+        );
+        match read_config(&legacy_toml_path) {
+            Ok(config) => migrate_config(config),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", legacy_toml_path.display(), e);
+                Config::default()
+            }
+        }
+    } else if legacy_path.exists() {
+        eprintln!(
+            "Migrating {} to {}",
+            legacy_path.display(),
+            config_path.display()
+        );
+        match fs::read_to_string(&legacy_path) {
+            Ok(contents) => {
+                let config = parse_legacy_config(&contents);
+                // keep the old file around in case something went wrong
+                if let Err(e) = fs::rename(&legacy_path, legacy_path.with_extension("txt.bak")) {
+                    eprintln!("Failed to back up {}: {}", legacy_path.display(), e);
+                }
+                config
+            }
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", legacy_path.display(), e);
+                Config::default()
+            }
+        }
+    } else {
+        Config::default()
+    };
 
-    // Default content for config.txt
-    let default_config = "\
-gzDoom_Path = empty
-wad_Path = empty
-mods_Directory = empty";
+    if let Err(e) = save_config(&config) {
+        eprintln!("Failed to write {}: {}", config_path.display(), e);
+    }
 
-    // Create and write default values to config.txt
-    let mut file = File::create(&config_path).expect("Failed to create config file");
-    file.write_all(default_config.as_bytes())
-        .expect("Failed to write to config file");
+    config
+}
 
-    default_config.to_string()
+/// Reads and parses a config.toml file
+fn read_config(path: &Path) -> Result<Config, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str(&contents).map_err(|e| e.to_string())
 }
 
+/// Upgrades a config written by an older version of imp-orter
+///
+/// #Arguments
+/// - the Config as it was read from disk
+///
+/// #Returns
+/// - the Config at CONFIG_VERSION
+fn migrate_config(mut config: Config) -> Config {
+    if config.version > CONFIG_VERSION {
+        eprintln!(
+            "Config version {} is newer than this build supports ({}), some settings may be ignored",
+            config.version, CONFIG_VERSION
+        );
+    }
+
+    // version 1 is the first TOML schema, so there is nothing to upgrade yet
+    config.version = CONFIG_VERSION;
+    config
+}
 
-/// Updates the gzDoom executable path
+/// Converts the contents of an old config.txt into a Config
 ///
 /// #Arguments
-/// - path to config.txt as a string reference
-/// - new path to gzDoom as a string reference
+/// - contents of config.txt as a string reference
 ///
 /// #Returns
-/// - nothing
-pub fn update_gzdoom_path(config_path: &str, new_path: &str) {
-    let config = check_config_file();
-
-    let mut updated_lines: Vec<String> = Vec::new();
-    for line in config.lines() {
-        if line.starts_with("gzDoom_Path") {
-            updated_lines.push(format!("gzDoom_Path = {}", new_path));
-        } else {
-            updated_lines.push(line.to_string());
+/// - Config with every value that could be recovered
+///
+/// The old format is one "key = value" per line, with "empty" for unset paths,
+/// '|' separated lists and "modIwad_<name>"/"modPort_<name>" keys for per-mod settings.
+fn parse_legacy_config(contents: &str) -> Config {
+    let values: HashMap<&str, &str> = contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim(), value.trim()))
+        })
+        .collect();
+
+    let path = |key: &str| {
+        values
+            .get(key)
+            .filter(|value| **value != "empty")
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    };
+    let list = |key: &str| {
+        path(key)
+            .split('|')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty() && item != "empty")
+            .collect::<Vec<String>>()
+    };
+
+    let mut config = Config {
+        source_port: path("gzDoom_Path"),
+        wad_path: path("wad_Path"),
+        mods_directory: path("mods_Directory"),
+        iwads: list("iwad_Paths"),
+        ports: list("port_Paths"),
+        ..Config::default()
+    };
+
+    for (key, value) in &values {
+        if let Some(mod_name) = key.strip_prefix("modIwad_") {
+            config.mods.entry(mod_name.to_string()).or_default().iwad = Some(value.to_string());
+        } else if let Some(mod_name) = key.strip_prefix("modPort_") {
+            config.mods.entry(mod_name.to_string()).or_default().port = Some(value.to_string());
         }
     }
 
-    let new_config = updated_lines.join("\n");
-
-    let mut file = File::create(config_path).expect("Failed to open config file for writing");
-    file.write_all(new_config.as_bytes())
-        .expect("Failed to write updated config");
+    config
+}
 
-    println!("gzDoom_Path updated successfully.");
+/// Function to write the config to config.toml
+///
+/// #Arguments
+/// - the Config to store
+///
+/// #Returns
+/// - an io error if the file or its folder can't be written, or if the config.toml on disk
+///   can't be parsed. Saving then would replace every setting with the defaults
+pub fn save_config(config: &Config) -> std::io::Result<()> {
+    write_config(&get_config_path(), config)
 }
 
-/// Updates the Doom II wad path
+/// Writes a config to a file, refusing to replace a file that can't be parsed
 ///
 /// #Arguments
-/// - path to config.txt as a string reference
-/// - new path to Doom II wad as a string reference
+/// - path of the config file
+/// - the Config to store
 ///
 /// #Returns
-/// - nothing
-pub fn update_wad_path(config_path: &str, new_path: &str) {
-    let config = check_config_file();
-
-    let mut updated_lines: Vec<String> = Vec::new();
-    for line in config.lines() {
-        if line.starts_with("wad_Path") {
-            updated_lines.push(format!("wad_Path = {}", new_path));
-        } else {
-            updated_lines.push(line.to_string());
+/// - an io error if the file can't be written or the existing file is unreadable
+///
+/// The config is written to a temporary file in the same folder first and then renamed
+/// over the old one, so a crash never leaves a half written config behind.
+fn write_config(config_path: &Path, config: &Config) -> std::io::Result<()> {
+    if config_path.exists() {
+        if let Err(e) = read_config(config_path) {
+            return Err(std::io::Error::other(format!(
+                "{} could not be read ({}), fix or remove it before changing settings",
+                config_path.display(),
+                e
+            )));
         }
     }
 
-    let new_config = updated_lines.join("\n");
+    // Ensure the parent directory exists
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
 
-    let mut file = File::create(config_path).expect("Failed to open config file for writing");
-    file.write_all(new_config.as_bytes())
-        .expect("Failed to write updated config");
+    let contents = toml::to_string_pretty(config).map_err(std::io::Error::other)?;
+    let temp_path = config_path.with_extension("toml.tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, config_path)
+}
 
-    println!("Wad file updated successfully.");
+impl Config {
+    /// Function to find a preset by name
+    ///
+    /// #Arguments
+    /// - name of the preset, case-insensitive
+    ///
+    /// #Returns
+    /// - the Preset, or None if there is no preset with that name
+    pub fn preset(&self, name: &str) -> Option<&Preset> {
+        self.presets
+            .iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+    }
 }
 
-/// Updates the mod directory path where users store their mod wads.
+/// Function to change one or more settings and save them
 ///
 /// #Arguments
-/// - path to mod directory as a string reference
-/// - new mod directory path as a string reference
+/// - closure that updates the loaded Config
 ///
 /// #Returns
-/// - nothing
-pub fn update_wad_directory(config_path: &str, new_path: &str) {
-    let config = check_config_file();
-
-    let mut updated_lines: Vec<String> = Vec::new();
-    for line in config.lines() {
-        if line.starts_with("mods_Directory") {
-            updated_lines.push(format!("mods_Directory = {}", new_path));
-        } else {
-            updated_lines.push(line.to_string());
-        }
+/// - nothing, errors are reported to stderr
+///
+/// The config is reloaded from disk first so changes made elsewhere are not lost.
+pub fn update_config<F: FnOnce(&mut Config)>(update: F) {
+    let mut config = load_config();
+    update(&mut config);
+
+    match save_config(&config) {
+        Ok(_) => eprintln!("Config updated successfully."),
+        Err(e) => eprintln!("Failed to write config: {}", e),
     }
+}
 
-    let new_config = updated_lines.join("\n");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut file = File::create(config_path).expect("Failed to open config file for writing");
-    file.write_all(new_config.as_bytes())
-        .expect("Failed to write updated config");
+    /// A folder of its own under the system temp folder, removed first if a previous run left it
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("imp-porter-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn migrates_legacy_config() {
+        let legacy = "gzDoom_Path = /usr/bin/gzdoom\n\
+                      wad_Path = /doom/doom2.wad\n\
+                      mods_Directory = empty\n\
+                      iwad_Paths = /doom/tnt.wad|empty| /doom/plutonia.wad\n\
+                      port_Paths = empty\n\
+                      modIwad_sunlust.wad = /doom/doom2.wad\n\
+                      modPort_sunlust.wad = /usr/bin/dsda-doom\n";
+        let config = parse_legacy_config(legacy);
+
+        assert_eq!(config.source_port, "/usr/bin/gzdoom");
+        assert_eq!(config.wad_path, "/doom/doom2.wad");
+        assert_eq!(config.mods_directory, "");
+        assert_eq!(config.iwads, vec!["/doom/tnt.wad", "/doom/plutonia.wad"]);
+        assert!(config.ports.is_empty());
+        assert_eq!(
+            config.mods["sunlust.wad"],
+            ModSettings {
+                iwad: Some("/doom/doom2.wad".to_string()),
+                port: Some("/usr/bin/dsda-doom".to_string()),
+            }
+        );
+        assert_eq!(config.version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join("config.toml");
+        let mut config = Config {
+            wad_path: "C:\\Doom = fun\\doom2.wad".to_string(),
+            ..Config::default()
+        };
+        config.presets.push(Preset {
+            name: "Sunlust UV".to_string(),
+            skill: Some(4),
+            ..Preset::default()
+        });
 
-    println!("Wad Directory updated successfully.");
+        write_config(&path, &config).unwrap();
+        assert_eq!(read_config(&path).unwrap(), config);
+        assert!(!path.with_extension("toml.tmp").exists());
+    }
+
+    #[test]
+    fn refuses_to_overwrite_unreadable_config() {
+        let dir = temp_dir("unreadable");
+        let path = dir.join("config.toml");
+        fs::write(&path, "wad_path = [unclosed").unwrap();
+
+        assert!(write_config(&path, &Config::default()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "wad_path = [unclosed");
+    }
 }
@@ -0,0 +1,300 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Largest text file that is read, idgames text files are a few KB
+pub const MAX_TEXT_SIZE: u64 = 1024 * 1024;
+
+/// The fields of the idgames text file template that are shown in the Play view.
+/// Every field is optional, authors leave out whatever does not apply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub release_date: Option<String>,
+    pub description: Option<String>,
+    pub additional_credits: Option<String>,
+    pub game: Option<String>,
+    pub maps: Option<String>,
+    pub difficulty_settings: Option<String>,
+    pub base: Option<String>,
+    pub build_time: Option<String>,
+    pub editors_used: Option<String>,
+    pub known_bugs: Option<String>,
+    pub may_not_run_with: Option<String>,
+    pub tested_with: Option<String>,
+}
+
+impl ModInfo {
+    /// Function to list the fields that were filled in, for display
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - Vector of (label, value) in template order
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Title", &self.title),
+            ("Author", &self.author),
+            ("Release date", &self.release_date),
+            ("Description", &self.description),
+            ("Additional credits", &self.additional_credits),
+            ("Game", &self.game),
+            ("Maps", &self.maps),
+            ("Difficulty settings", &self.difficulty_settings),
+            ("Base", &self.base),
+            ("Build time", &self.build_time),
+            ("Editors used", &self.editors_used),
+            ("Known bugs", &self.known_bugs),
+            ("May not run with", &self.may_not_run_with),
+            ("Tested with", &self.tested_with),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.clone().map(|value| (label, value)))
+        .collect()
+    }
+
+    /// The value stored for a field name returned by `field_name`
+    fn field_mut(&mut self, name: &str) -> &mut Option<String> {
+        match name {
+            "title" => &mut self.title,
+            "author" => &mut self.author,
+            "release date" => &mut self.release_date,
+            "description" => &mut self.description,
+            "additional credits" => &mut self.additional_credits,
+            "game" => &mut self.game,
+            "maps" => &mut self.maps,
+            "difficulty settings" => &mut self.difficulty_settings,
+            "base" => &mut self.base,
+            "build time" => &mut self.build_time,
+            "editors used" => &mut self.editors_used,
+            "known bugs" => &mut self.known_bugs,
+            "may not run with" => &mut self.may_not_run_with,
+            _ => &mut self.tested_with,
+        }
+    }
+}
+
+/// Maps a template key such as "May Not Run With..." or "Map #" to the field it fills
+fn field_name(key: &str) -> Option<&'static str> {
+    match key
+        .trim()
+        .trim_end_matches('.')
+        .trim()
+        .to_lowercase()
+        .as_str()
+    {
+        "title" => Some("title"),
+        "author" | "authors" => Some("author"),
+        "release date" => Some("release date"),
+        "description" => Some("description"),
+        "additional credits to" | "additional credits" => Some("additional credits"),
+        "game" => Some("game"),
+        "map #" | "map#" | "maps" => Some("maps"),
+        "difficulty settings" => Some("difficulty settings"),
+        "base" => Some("base"),
+        "build time" => Some("build time"),
+        "editor(s) used" | "editors used" => Some("editors used"),
+        "known bugs" => Some("known bugs"),
+        "may not run with" => Some("may not run with"),
+        "tested with" => Some("tested with"),
+        _ => None,
+    }
+}
+
+/// Function to read the standard idgames template fields from a mod's text file
+///
+/// #Arguments
+/// - contents of the text file as a string reference
+///
+/// #Returns
+/// - ModInfo with every field that was found, or None if the text does not follow the template
+///
+/// Fields are "Key : value" lines. Indented lines that follow a field continue its value,
+/// which is how long descriptions are written. Values such as "none" or "n/a" are ignored.
+pub fn parse_mod_info(text: &str) -> Option<ModInfo> {
+    let mut info = ModInfo::default();
+    // the field the previous line filled, indented lines are added to it
+    let mut current: Option<&'static str> = None;
+
+    for line in text.lines() {
+        let line = line.trim_end();
+
+        if line.trim().is_empty() {
+            current = None;
+            continue;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            current = None;
+            if let Some((key, value)) = line.split_once(':') {
+                if let Some(name) = field_name(key) {
+                    let value = value.trim();
+                    if !value.is_empty() {
+                        *info.field_mut(name) = Some(value.to_string());
+                    }
+                    current = Some(name);
+                }
+            }
+            continue;
+        }
+
+        if let Some(name) = current {
+            let field = info.field_mut(name);
+            let line = line.trim();
+            *field = Some(match field.take() {
+                Some(value) => format!("{} {}", value, line),
+                None => line.to_string(),
+            });
+        }
+    }
+
+    // placeholders count as not filled in
+    for field in [
+        &mut info.title,
+        &mut info.author,
+        &mut info.release_date,
+        &mut info.description,
+        &mut info.additional_credits,
+        &mut info.game,
+        &mut info.maps,
+        &mut info.difficulty_settings,
+        &mut info.base,
+        &mut info.build_time,
+        &mut info.editors_used,
+        &mut info.known_bugs,
+        &mut info.may_not_run_with,
+        &mut info.tested_with,
+    ] {
+        if field.as_deref().is_some_and(is_placeholder) {
+            *field = None;
+        }
+    }
+
+    (info != ModInfo::default()).then_some(info)
+}
+
+/// Template values that mean the author left the field empty
+fn is_placeholder(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "" | "none" | "n/a" | "na" | "-" | "?" | "unknown"
+    )
+}
+
+/// Function to find the text file kept next to a mod file
+///
+/// #Arguments
+/// - path to the mod file
+///
+/// #Returns
+/// - path to the .txt with the same name, or None if there is none
+///
+/// Names are compared case-insensitively, so MAPSET.WAD and mapset.deh both find MAPSET.txt.
+pub fn text_file_for(mod_path: &Path) -> Option<PathBuf> {
+    let stem = mod_path.file_stem()?.to_string_lossy().to_lowercase();
+    fs::read_dir(mod_path.parent()?)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+                && path
+                    .file_stem()
+                    .is_some_and(|text_stem| text_stem.to_string_lossy().to_lowercase() == stem)
+                && path.is_file()
+        })
+}
+
+/// Function to read the idgames fields for a mod from its text file
+///
+/// #Arguments
+/// - path to the mod file
+///
+/// #Returns
+/// - ModInfo, or None if there is no text file or it does not follow the template
+///
+/// Only the first MAX_TEXT_SIZE bytes of the text file are read.
+pub fn read_mod_info(mod_path: &Path) -> Option<ModInfo> {
+    let mut bytes = Vec::new();
+    File::open(text_file_for(mod_path)?)
+        .ok()?
+        .take(MAX_TEXT_SIZE)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    // most text files are plain ASCII or CP437, a lossy read keeps them readable
+    parse_mod_info(&String::from_utf8_lossy(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_template_fields() {
+        let text = "===========================================================================\n\
+                    Advanced engine needed  : Boom\n\
+                    Primary purpose         : Single play\n\
+                    ===========================================================================\n\
+                    Title                   : Sunlust\n\
+                    Filename                : sunlust.wad\n\
+                    Release date            : 2015-01-05\n\
+                    Author                  : Ribbiks and dannebubinga\n\
+                    Description             : 32 maps for Doom II,\n\
+                    \x20                         built for Boom compatible ports.\n\
+                    \n\
+                    Additional Credits to   : N/A\n\
+                    * Play Information *\n\
+                    Game                    : DOOM2\n\
+                    Map #                   : MAP01-MAP32\n\
+                    Editor(s) used          : Doom Builder 2\n\
+                    May Not Run With...     : Vanilla Doom\n";
+
+        let info = parse_mod_info(text).unwrap();
+        assert_eq!(info.title.as_deref(), Some("Sunlust"));
+        assert_eq!(info.release_date.as_deref(), Some("2015-01-05"));
+        assert_eq!(info.author.as_deref(), Some("Ribbiks and dannebubinga"));
+        assert_eq!(
+            info.description.as_deref(),
+            Some("32 maps for Doom II, built for Boom compatible ports.")
+        );
+        assert_eq!(info.additional_credits, None);
+        assert_eq!(info.game.as_deref(), Some("DOOM2"));
+        assert_eq!(info.maps.as_deref(), Some("MAP01-MAP32"));
+        assert_eq!(info.editors_used.as_deref(), Some("Doom Builder 2"));
+        assert_eq!(info.may_not_run_with.as_deref(), Some("Vanilla Doom"));
+        assert_eq!(info.fields()[0], ("Title", "Sunlust".to_string()));
+    }
+
+    #[test]
+    fn ignores_text_without_the_template() {
+        assert_eq!(parse_mod_info(""), None);
+        assert_eq!(
+            parse_mod_info("Thanks for playing!\nSee you next time."),
+            None
+        );
+        assert_eq!(parse_mod_info("Title : none\nAuthor : ?\n"), None);
+    }
+
+    #[test]
+    fn finds_the_text_file_of_a_mod() {
+        let dir =
+            std::env::temp_dir().join(format!("imp-porter-idgames-text-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("MAPSET.TXT"), "Title : Mapset\n").unwrap();
+
+        assert_eq!(
+            text_file_for(&dir.join("mapset.wad")),
+            Some(dir.join("MAPSET.TXT"))
+        );
+        assert_eq!(
+            read_mod_info(&dir.join("mapset.deh")).and_then(|info| info.title),
+            Some("Mapset".to_string())
+        );
+        assert_eq!(text_file_for(&dir.join("other.wad")), None);
+    }
+}
@@ -0,0 +1,1062 @@
+use serde::Serialize;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::archive::{ArchiveFormat, SNIFF_LEN};
+use crate::config_functions::ImportSettings;
+use crate::library::Library;
+use crate::package::{self, Package};
+use crate::utility::{self, ModKind};
+use crate::{idgames, paths};
+
+/// A file that was not imported
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedFile {
+    pub name: String,
+    pub reason: String,
+}
+
+/// What happened to each file of an imported archive, mod file or folder
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// The archive, mod file or folder that was imported
+    pub source: PathBuf,
+    /// Mod files written to the mods directory
+    pub extracted: Vec<String>,
+    /// Mod files that were already in the mods directory with the same contents
+    pub skipped: Vec<String>,
+    /// Files that were not imported, with the reason
+    pub rejected: Vec<RejectedFile>,
+    /// Set when the archive, mod file or folder itself could not be read
+    pub error: Option<String>,
+    /// One package per archive that had mod files in it
+    pub packages: Vec<Package>,
+}
+
+impl ImportReport {
+    /// Builds the text shown in the UI after an import
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - String with one line for the imported item, or the error that stopped it, and one
+    ///   line per file
+    pub fn summary(&self) -> String {
+        let source_name = self
+            .source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.source.display().to_string());
+
+        // files written before an error are listed too, they stay in the mods directory
+        let mut lines = vec![match &self.error {
+            Some(e) => format!("Failed to import {}: {}", source_name, e),
+            None => format!(
+                "Imported {}: {} extracted, {} already present, {} rejected",
+                source_name,
+                self.extracted.len(),
+                self.skipped.len(),
+                self.rejected.len()
+            ),
+        }];
+        lines.extend(self.extracted.iter().map(|name| format!("+ {}", name)));
+        lines.extend(
+            self.skipped
+                .iter()
+                .map(|name| format!("= {} (already present)", name)),
+        );
+        lines.extend(
+            self.rejected
+                .iter()
+                .map(|rejected| format!("- {} ({})", rejected.name, rejected.reason)),
+        );
+        lines.extend(
+            self.packages
+                .iter()
+                .map(|package| format!("Package {}: {}", package.name, package.files.join(", "))),
+        );
+        lines.join("\n")
+    }
+}
+
+/// Function to import an archive, a loose mod file or a folder into the mods directory
+///
+/// #Arguments
+/// - path to the archive, mod file or folder
+/// - path to the mods directory
+/// - limits from the [import] table of the config
+///
+/// #Returns
+/// - ImportReport listing the extracted, skipped and rejected files
+///
+/// Recognized mod files (.wad, .pk3, .pk7, .ipk3, .deh, .bex) are written to the top of
+/// the mods directory and validated before they are kept. Archives (zip, 7z, tar.gz, tar.xz,
+/// tar.bz2) are recognized by their contents and extracted, including archives inside
+/// archives up to the configured depth, and folders are searched for mod files and archives.
+/// A file that is already in the mods directory is skipped when its contents are identical
+/// and rejected otherwise, so an existing mod is never overwritten.
+///
+/// The .txt description that idgames releases ship with is kept next to the mod it belongs
+/// to, renamed to the mod's name when needed, so its template fields can be shown later.
+/// A .txt imported on its own is kept next to the mod in the mods directory with the same
+/// name, and rejected if there is none.
+///
+/// The import stops with an error once it has written more than the configured size or
+/// read more than the configured number of files, so a zip bomb can't fill the disk.
+///
+/// Written files are recorded in the library with the archive they came from and the date.
+pub fn import_path(path: &Path, target_dir: &str, settings: &ImportSettings) -> ImportReport {
+    let mut importer = Importer {
+        target_dir: Path::new(target_dir),
+        settings,
+        total_size: 0,
+        entries: 0,
+        stopped: false,
+        texts: Vec::new(),
+        placed: Vec::new(),
+        report: ImportReport {
+            source: path.to_path_buf(),
+            ..ImportReport::default()
+        },
+    };
+
+    if target_dir.is_empty() || target_dir == "empty" {
+        importer.report.error = Some("no mods directory set".to_string());
+        return importer.report;
+    }
+
+    let result = if path.is_dir() {
+        importer.import_folder(path)
+    } else if is_text_file(&path.to_string_lossy()) {
+        importer.import_text(path)
+    } else if ModKind::from_path(path).is_some() || ArchiveFormat::sniff(path).is_some() {
+        importer.import_file(path)
+    } else {
+        Err(std::io::Error::other("not an archive, mod file or folder"))
+    };
+
+    if let Err(e) = result {
+        importer.report.error = Some(e.to_string());
+    }
+
+    for package in &importer.report.packages {
+        if let Err(e) = package::save_package(package) {
+            eprintln!("Failed to record package {}: {}", package.name, e);
+        }
+    }
+
+    if let Err(e) =
+        Library::open().and_then(|mut library| library.record_import(target_dir, &importer.report))
+    {
+        eprintln!("Failed to record the import in the library: {}", e);
+    }
+    importer.report
+}
+
+/// State of one import, shared by every folder and archive it opens
+struct Importer<'a> {
+    target_dir: &'a Path,
+    settings: &'a ImportSettings,
+    /// Bytes written so far, counted against max_total_size
+    total_size: u64,
+    /// Files read from archives so far, counted against max_entries
+    entries: u64,
+    /// Set when a limit was hit, the whole import stops instead of skipping one file
+    stopped: bool,
+    /// Text files found in the archive or folder being read, kept once its mods are known
+    texts: Vec<(String, Vec<u8>)>,
+    /// Mod files imported from the archive or folder being read
+    placed: Vec<String>,
+    report: ImportReport,
+}
+
+impl Importer<'_> {
+    /// Imports every mod file and archive in a folder and its subfolders
+    fn import_folder(&mut self, folder: &Path) -> std::io::Result<()> {
+        self.in_scope(|importer| importer.import_folder_files(folder))
+    }
+
+    /// Imports the files of a folder, see `import_folder`
+    fn import_folder_files(&mut self, folder: &Path) -> std::io::Result<()> {
+        // list the folder before importing, it may be the mods directory itself
+        let mut entries: Vec<PathBuf> = fs::read_dir(folder)?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+
+        for entry in entries {
+            let result = if entry.is_dir() {
+                self.import_folder(&entry)
+            } else {
+                self.import_file(&entry)
+            };
+
+            // a bad file inside the folder should not stop the rest of the import
+            match result {
+                Err(e) if self.stopped => return Err(e),
+                Err(e) => self.reject(entry.display().to_string(), e.to_string()),
+                Ok(()) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Imports a single file from disk: mod files are copied, archives are extracted
+    fn import_file(&mut self, path: &Path) -> std::io::Result<()> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // pk3 files are zips too, so the extension is checked before the contents
+        if let Some(kind) = ModKind::from_path(path) {
+            return self.place_file(&mut File::open(path)?, file_name, kind);
+        }
+
+        if is_text_file(&file_name) {
+            return self.keep_text(&mut File::open(path)?, file_name);
+        }
+
+        match ArchiveFormat::sniff(path) {
+            Some(format) => self.extract_archive(path, format, 0),
+            None => {
+                self.reject(file_name, "not a mod file");
+                Ok(())
+            }
+        }
+    }
+
+    /// Extracts every file of an archive, recording the outcome of each one in the report
+    ///
+    /// #Arguments
+    /// - path to the archive
+    /// - format of the archive
+    /// - how many archives it is nested in, 0 for the archive being imported
+    ///
+    /// #Returns
+    /// - an io error if the archive can't be read or a limit was hit
+    fn extract_archive(
+        &mut self,
+        archive_path: &Path,
+        format: ArchiveFormat,
+        depth: u32,
+    ) -> std::io::Result<()> {
+        let backend = format.backend().map_err(std::io::Error::other)?;
+
+        let first_placed = self.placed.len();
+        let result = self.in_scope(|importer| {
+            backend.for_each_file(archive_path, &mut |entry_name, reader| {
+                importer.extract_entry(entry_name, reader, depth)
+            })
+        });
+
+        // the mods of an archive that was imported directly, including nested ones, form a package
+        if depth == 0 && result.is_ok() {
+            let mut files = self.placed[first_placed..].to_vec();
+            files.dedup();
+            if !files.is_empty() {
+                let package = self.build_package(archive_path, files);
+                self.report.packages.push(package);
+            }
+        }
+
+        result
+    }
+
+    /// Builds the package for the mods of an archive
+    ///
+    /// #Arguments
+    /// - path to the archive
+    /// - names of the mod files imported from it, in archive order
+    ///
+    /// #Returns
+    /// - Package with the files in load order: files with maps first, then resources such as
+    ///   music or textures that override them, then DeHackEd patches
+    fn build_package(&self, archive_path: &Path, mut files: Vec<String>) -> Package {
+        let source_archive = archive_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = package::name_for_archive(&source_archive);
+
+        files.sort_by_key(|file_name| {
+            let Some(kind) = ModKind::from_path(file_name) else {
+                return 1;
+            };
+            if kind.is_dehacked() {
+                return 2;
+            }
+            let path = self.target_dir.join(file_name);
+            match utility::read_mod_entry(&path.to_string_lossy(), kind) {
+                Ok(entry) if !entry.maps.is_empty() => 0,
+                _ => 1,
+            }
+        });
+
+        Package {
+            name,
+            source_archive,
+            files,
+        }
+    }
+
+    /// Runs the import of one archive or folder, then keeps the text files found in it
+    ///
+    /// #Arguments
+    /// - the import step that reads the archive or folder
+    ///
+    /// #Returns
+    /// - the result of the step, or an io error if a text file can't be written
+    ///
+    /// Mods imported from nested archives and subfolders count for the outer one too, so a
+    /// description next to a zip of the mod still finds it.
+    fn in_scope(
+        &mut self,
+        step: impl FnOnce(&mut Self) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let outer_texts = std::mem::take(&mut self.texts);
+        let outer_placed = std::mem::take(&mut self.placed);
+
+        let result = step(self);
+
+        let texts = std::mem::replace(&mut self.texts, outer_texts);
+        let placed = std::mem::replace(&mut self.placed, outer_placed);
+        if !self.stopped {
+            self.place_texts(texts, &placed)?;
+        }
+        self.placed.extend(placed);
+        result
+    }
+
+    /// Imports a text file on its own, next to the mod in the mods directory it is named after
+    fn import_text(&mut self, path: &Path) -> std::io::Result<()> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let text_stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        let mod_stem = fs::read_dir(self.target_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|mod_path| ModKind::from_path(mod_path).is_some() && mod_path.is_file())
+            .filter_map(|mod_path| Some(mod_path.file_stem()?.to_string_lossy().into_owned()))
+            .find(|mod_stem| mod_stem.eq_ignore_ascii_case(&text_stem));
+
+        match mod_stem {
+            Some(mod_stem) => match self.read_text(&mut File::open(path)?, file_name)? {
+                Some(contents) => self.write_text(&mod_stem, &contents),
+                None => Ok(()),
+            },
+            None => {
+                self.reject(
+                    file_name,
+                    "no mod file with the same name in the mods directory",
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads a text file so it can be kept with the mods found next to it
+    fn keep_text(&mut self, reader: &mut dyn Read, file_name: String) -> std::io::Result<()> {
+        if let Some(contents) = self.read_text(reader, file_name.clone())? {
+            self.texts.push((file_name, contents));
+        }
+        Ok(())
+    }
+
+    /// Reads a text file into memory, rejecting it if it is larger than MAX_TEXT_SIZE
+    fn read_text(
+        &mut self,
+        reader: &mut dyn Read,
+        file_name: String,
+    ) -> std::io::Result<Option<Vec<u8>>> {
+        let mut contents = Vec::new();
+        self.copy_limited(&mut reader.take(idgames::MAX_TEXT_SIZE + 1), &mut contents)?;
+        if contents.len() as u64 > idgames::MAX_TEXT_SIZE {
+            self.reject(
+                file_name,
+                format!(
+                    "text files larger than {} MB are not imported",
+                    idgames::MAX_TEXT_SIZE / (1024 * 1024)
+                ),
+            );
+            return Ok(None);
+        }
+        Ok(Some(contents))
+    }
+
+    /// Writes the text files of an archive or folder next to the mods they describe
+    ///
+    /// #Arguments
+    /// - the text files, by name, with their contents
+    /// - names of the mod files imported with them
+    ///
+    /// #Returns
+    /// - an io error if a text file can't be written
+    ///
+    /// A text file with the same name as a mod is kept as is. Otherwise the idgames
+    /// description (or the only text file) is renamed after the first mod, and any other
+    /// text file is rejected.
+    fn place_texts(
+        &mut self,
+        texts: Vec<(String, Vec<u8>)>,
+        placed: &[String],
+    ) -> std::io::Result<()> {
+        let stem = |name: &str| {
+            Path::new(name)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let mod_stems: Vec<String> = placed.iter().map(|name| stem(name)).collect();
+
+        let mut described = Vec::new();
+        let mut unmatched = Vec::new();
+        for (file_name, contents) in texts {
+            match mod_stems
+                .iter()
+                .find(|mod_stem| mod_stem.eq_ignore_ascii_case(&stem(&file_name)))
+            {
+                Some(mod_stem) => {
+                    described.push(mod_stem.clone());
+                    self.write_text(mod_stem, &contents)?;
+                }
+                None => unmatched.push((file_name, contents)),
+            }
+        }
+
+        let main_mod = mod_stems.first().filter(|main| !described.contains(main));
+        let only_text = unmatched.len() == 1;
+        let main_text = unmatched.iter().position(|(_, contents)| {
+            only_text || idgames::parse_mod_info(&String::from_utf8_lossy(contents)).is_some()
+        });
+
+        for (index, (file_name, contents)) in unmatched.into_iter().enumerate() {
+            match main_mod {
+                Some(main) if main_text == Some(index) => self.write_text(main, &contents)?,
+                _ => self.reject(file_name, "no mod file imported with it"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a text file named after a mod, unless the mod already has a different one
+    fn write_text(&mut self, mod_stem: &str, contents: &[u8]) -> std::io::Result<()> {
+        // named after the mod so it is found next to it
+        let text_name = format!("{}.txt", mod_stem);
+        let final_path = self.target_dir.join(&text_name);
+
+        if !final_path.exists() {
+            fs::write(&final_path, contents)?;
+            self.report.extracted.push(text_name);
+        } else if same_text(&final_path, contents)? {
+            self.report.skipped.push(text_name);
+        } else {
+            self.reject(
+                text_name,
+                "a different file with this name is already in the mods directory",
+            );
+        }
+        Ok(())
+    }
+
+    /// Handles one file inside an archive: mod files are placed, archives are opened
+    fn extract_entry(
+        &mut self,
+        entry_name: &str,
+        reader: &mut dyn Read,
+        depth: u32,
+    ) -> std::io::Result<()> {
+        self.entries += 1;
+        if self.entries > self.settings.max_entries {
+            self.stopped = true;
+            return Err(std::io::Error::other(format!(
+                "stopped after {} files, the archive may be a zip bomb",
+                self.settings.max_entries
+            )));
+        }
+
+        // mod files are flattened into the mods directory, folders inside the archive are dropped
+        let Some(file_name) = Path::new(entry_name)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+        else {
+            return Ok(());
+        };
+
+        if let Some(kind) = ModKind::from_path(&file_name) {
+            return self.place_file(reader, file_name, kind);
+        }
+
+        if is_text_file(&file_name) {
+            return self.keep_text(reader, file_name);
+        }
+
+        // anything else may be another archive, recognized by its first bytes
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        reader.take(SNIFF_LEN as u64).read_to_end(&mut header)?;
+        let Some(format) = ArchiveFormat::sniff_bytes(&header) else {
+            self.reject(file_name, "not a mod file");
+            return Ok(());
+        };
+
+        if depth >= self.settings.max_depth {
+            self.reject(
+                file_name,
+                format!(
+                    "archive nested more than {} levels deep",
+                    self.settings.max_depth
+                ),
+            );
+            return Ok(());
+        }
+
+        // backends read from disk, so the nested archive is written to the cache first
+        let nested_path = paths::get().cache_dir.join(format!(
+            "import-{}-{}.part",
+            std::process::id(),
+            self.entries
+        ));
+        let result = self
+            .write_limited(&mut header.as_slice().chain(reader), &nested_path)
+            .and_then(|()| self.extract_archive(&nested_path, format, depth + 1));
+        if let Err(e) = fs::remove_file(&nested_path) {
+            eprintln!("Failed to remove {}: {}", nested_path.display(), e);
+        }
+
+        match result {
+            Err(e) if !self.stopped => {
+                self.reject(file_name, e.to_string());
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Writes a mod file into the mods directory if it is valid and new
+    ///
+    /// #Arguments
+    /// - reader with the contents of the file
+    /// - file name to store it under
+    /// - the kind of mod file
+    ///
+    /// #Returns
+    /// - an io error if the file can't be written or the size limit was hit
+    fn place_file(
+        &mut self,
+        reader: &mut dyn Read,
+        file_name: String,
+        kind: ModKind,
+    ) -> std::io::Result<()> {
+        // write next to the final location first so a bad file never shows up as a mod
+        let final_path = self.target_dir.join(&file_name);
+        let part_path = self.target_dir.join(format!(".{}.part", file_name));
+        if let Err(e) = self.write_limited(reader, &part_path) {
+            let _ = fs::remove_file(&part_path);
+            return Err(e);
+        }
+
+        let outcome = match check_extracted(&part_path, &final_path, kind) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                let _ = fs::remove_file(&part_path);
+                return Err(e);
+            }
+        };
+
+        match outcome {
+            Outcome::New => {
+                fs::rename(&part_path, &final_path)?;
+
+                // Set file permissions on Unix-based systems
+                #[cfg(target_family = "unix")]
+                fs::set_permissions(&final_path, fs::Permissions::from_mode(0o644))?;
+
+                self.placed.push(file_name.clone());
+                self.report.extracted.push(file_name);
+            }
+            Outcome::Duplicate => {
+                fs::remove_file(&part_path)?;
+                self.placed.push(file_name.clone());
+                self.report.skipped.push(file_name);
+            }
+            Outcome::Rejected(reason) => {
+                fs::remove_file(&part_path)?;
+                self.reject(file_name, reason);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies a file to disk, stopping the import once it has written more than max_total_size
+    fn write_limited(&mut self, reader: &mut dyn Read, path: &Path) -> std::io::Result<()> {
+        self.copy_limited(reader, &mut File::create(path)?)
+    }
+
+    /// Copies a file, stopping the import once it has written more than max_total_size
+    fn copy_limited(
+        &mut self,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let remaining = self.settings.max_total_size.saturating_sub(self.total_size);
+        let written = std::io::copy(&mut reader.take(remaining.saturating_add(1)), writer)?;
+        self.total_size += written;
+
+        if written > remaining {
+            self.stopped = true;
+            return Err(std::io::Error::other(format!(
+                "stopped after writing {} MB, the archive may be a zip bomb",
+                self.settings.max_total_size / (1024 * 1024)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Records a file that was not imported
+    fn reject(&mut self, name: String, reason: impl Into<String>) {
+        self.report.rejected.push(RejectedFile {
+            name,
+            reason: reason.into(),
+        });
+    }
+}
+
+/// Compares a text file with new contents, reading no more of it than the new contents hold
+fn same_text(path: &Path, contents: &[u8]) -> std::io::Result<bool> {
+    let mut existing = Vec::new();
+    File::open(path)?
+        .take(contents.len() as u64 + 1)
+        .read_to_end(&mut existing)?;
+    Ok(existing == contents)
+}
+
+/// Checks the extension for .txt (case-insensitive)
+fn is_text_file(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+}
+
+/// What to do with a file after it was extracted
+enum Outcome {
+    New,
+    Duplicate,
+    Rejected(String),
+}
+
+/// Decides whether an extracted file can be moved into the mods directory
+///
+/// #Arguments
+/// - path the file was extracted to
+/// - path it would be moved to
+/// - the kind of mod file
+///
+/// #Returns
+/// - New if the file is valid and not in the mods directory yet
+/// - Duplicate if the same file is already in the mods directory
+/// - Rejected with the reason otherwise
+/// - an io error if either file can't be read to compare them
+fn check_extracted(part_path: &Path, final_path: &Path, kind: ModKind) -> std::io::Result<Outcome> {
+    if final_path.exists() {
+        return Ok(if same_contents(part_path, final_path)? {
+            Outcome::Duplicate
+        } else {
+            Outcome::Rejected(
+                "a different file with this name is already in the mods directory".to_string(),
+            )
+        });
+    }
+
+    Ok(
+        match utility::read_mod_entry(&part_path.to_string_lossy(), kind) {
+            Ok(_) => Outcome::New,
+            Err(e) => Outcome::Rejected(format!("not a valid .{} file: {}", kind.as_str(), e)),
+        },
+    )
+}
+
+/// Compares two files a chunk at a time, so large mods are never read into memory whole
+fn same_contents(a: &Path, b: &Path) -> std::io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+
+    let mut reader_a = BufReader::new(File::open(a)?);
+    let mut reader_b = BufReader::new(File::open(b)?);
+    let mut chunk_a = [0u8; 8192];
+    let mut chunk_b = [0u8; 8192];
+    loop {
+        let read = reader_a.read(&mut chunk_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        reader_b.read_exact(&mut chunk_b[..read])?;
+        if chunk_a[..read] != chunk_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    /// A folder of its own under the system temp folder, removed first if a previous run left it
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("imp-porter-import-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Builds a PWAD with empty lumps of the given names
+    fn wad_bytes(names: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"PWAD");
+        data.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        data.extend_from_slice(&12u32.to_le_bytes());
+        for name in names {
+            data.extend_from_slice(&12u32.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            let mut padded = [0u8; 8];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+            data.extend_from_slice(&padded);
+        }
+        data
+    }
+
+    /// Builds a zip holding the given files
+    fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// Imports a file into a mods directory with the default limits
+    fn import(path: &Path, mods: &Path) -> ImportReport {
+        import_with(path, mods, &ImportSettings::default())
+    }
+
+    /// Imports a file into a mods directory with the given limits. Imports record their
+    /// packages in the same packages.toml, so they run one at a time
+    fn import_with(path: &Path, mods: &Path, settings: &ImportSettings) -> ImportReport {
+        static PACKAGES: Mutex<()> = Mutex::new(());
+        let _lock = PACKAGES.lock().unwrap_or_else(|e| e.into_inner());
+        import_path(path, &mods.to_string_lossy(), settings)
+    }
+
+    #[test]
+    fn imports_a_loose_mod_file() {
+        let dir = temp_dir("loose");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let source = dir.join("mapset.wad");
+        fs::write(&source, wad_bytes(&["MAP01", "THINGS"])).unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.error, None);
+        assert_eq!(report.extracted, vec!["mapset.wad"]);
+        assert_eq!(
+            fs::read(mods.join("mapset.wad")).unwrap(),
+            fs::read(&source).unwrap()
+        );
+        // a loose file is not a package, and nothing is left half written
+        assert!(report.packages.is_empty());
+        assert!(!mods.join(".mapset.wad.part").exists());
+    }
+
+    #[test]
+    fn never_overwrites_an_existing_mod() {
+        let dir = temp_dir("existing");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let source = dir.join("mapset.wad");
+        fs::write(&source, wad_bytes(&["MAP01", "THINGS"])).unwrap();
+        fs::copy(&source, mods.join("mapset.wad")).unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.skipped, vec!["mapset.wad"]);
+        assert!(report.rejected.is_empty());
+
+        fs::write(&source, wad_bytes(&["MAP02", "THINGS"])).unwrap();
+        let report = import(&source, &mods);
+        assert!(report.extracted.is_empty());
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(
+            fs::read(mods.join("mapset.wad")).unwrap(),
+            wad_bytes(&["MAP01", "THINGS"])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_mod_files() {
+        let dir = temp_dir("invalid");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let source = dir.join("broken.wad");
+        fs::write(&source, b"PWAD").unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].name, "broken.wad");
+        assert!(!mods.join("broken.wad").exists());
+        assert!(!mods.join(".broken.wad.part").exists());
+    }
+
+    #[test]
+    fn extracts_the_mods_of_a_zip() {
+        let dir = temp_dir("zip");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let map = wad_bytes(&["MAP01", "THINGS"]);
+        let source = dir.join("mapset.zip");
+        fs::write(
+            &source,
+            zip_bytes(&[
+                ("mapset/patch.deh", b"Patch File for DeHackEd v3.0\n"),
+                ("mapset/maps.wad", &map),
+                ("mapset/screenshot.png", b"\x89PNG"),
+            ]),
+        )
+        .unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.error, None);
+        assert_eq!(report.extracted, vec!["patch.deh", "maps.wad"]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].name, "screenshot.png");
+        assert_eq!(fs::read(mods.join("maps.wad")).unwrap(), map);
+
+        // maps load before patches
+        assert_eq!(report.packages.len(), 1);
+        assert_eq!(report.packages[0].name, "mapset");
+        assert_eq!(report.packages[0].files, vec!["maps.wad", "patch.deh"]);
+    }
+
+    #[test]
+    fn needs_a_mods_directory() {
+        let report = import_path(Path::new("mapset.zip"), "", &ImportSettings::default());
+        assert_eq!(report.error.as_deref(), Some("no mods directory set"));
+    }
+
+    #[test]
+    fn compares_files_by_contents() {
+        let dir = temp_dir("compare");
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| dir.join(name));
+        fs::write(&a, vec![7u8; 20_000]).unwrap();
+        fs::write(&b, vec![7u8; 20_000]).unwrap();
+        let mut changed = vec![7u8; 20_000];
+        changed[19_999] = 8;
+        fs::write(&c, changed).unwrap();
+        fs::write(&d, vec![7u8; 10]).unwrap();
+
+        assert!(same_contents(&a, &b).unwrap());
+        assert!(!same_contents(&a, &c).unwrap());
+        assert!(!same_contents(&a, &d).unwrap());
+        assert!(same_contents(&a, &dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn opens_nested_archives_up_to_the_depth_limit() {
+        let dir = temp_dir("depth");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let inner = zip_bytes(&[("maps.wad", &wad_bytes(&["MAP01", "THINGS"]))]);
+        let source = dir.join("outer.zip");
+        fs::write(&source, zip_bytes(&[("inner.zip", &inner)])).unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.extracted, vec!["maps.wad"]);
+        assert_eq!(report.packages[0].name, "outer");
+
+        fs::remove_file(mods.join("maps.wad")).unwrap();
+        let settings = ImportSettings {
+            max_depth: 0,
+            ..ImportSettings::default()
+        };
+        let report = import_with(&source, &mods, &settings);
+        assert!(report.extracted.is_empty());
+        assert_eq!(report.rejected[0].name, "inner.zip");
+        assert!(!mods.join("maps.wad").exists());
+    }
+
+    #[test]
+    fn stops_once_the_size_limit_is_hit() {
+        let dir = temp_dir("size");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let big = wad_bytes(&["MAP01", "THINGS", "LINEDEFS", "SIDEDEFS"]);
+        let source = dir.join("bomb.zip");
+        fs::write(
+            &source,
+            zip_bytes(&[("a.wad", &big), ("b.wad", &big), ("c.wad", &big)]),
+        )
+        .unwrap();
+
+        let settings = ImportSettings {
+            max_total_size: big.len() as u64 + 10,
+            ..ImportSettings::default()
+        };
+        let report = import_with(&source, &mods, &settings);
+        assert!(report.error.is_some());
+        // the file written before the limit stays, the import stops instead of skipping
+        assert_eq!(report.extracted, vec!["a.wad"]);
+        assert!(!mods.join("b.wad").exists());
+        assert!(!mods.join(".b.wad.part").exists());
+        assert!(!mods.join("c.wad").exists());
+    }
+
+    #[test]
+    fn stops_once_the_entry_limit_is_hit() {
+        let dir = temp_dir("entries");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let source = dir.join("many.zip");
+        let names: Vec<String> = (0..5).map(|i| format!("file{}.dat", i)).collect();
+        let files: Vec<(&str, &[u8])> = names
+            .iter()
+            .map(|name| (name.as_str(), &b"x"[..]))
+            .collect();
+        fs::write(&source, zip_bytes(&files)).unwrap();
+
+        let settings = ImportSettings {
+            max_entries: 3,
+            ..ImportSettings::default()
+        };
+        let report = import_with(&source, &mods, &settings);
+        assert!(report.error.is_some());
+        assert_eq!(report.rejected.len(), 3);
+        assert!(report.packages.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_description_next_to_the_mod() {
+        let dir = temp_dir("description");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let description = b"Title : Mapset\nAuthor : Someone\n";
+        let source = dir.join("mapset.zip");
+        fs::write(
+            &source,
+            zip_bytes(&[
+                ("readme.txt", description),
+                ("maps.wad", &wad_bytes(&["MAP01", "THINGS"])),
+                ("music.wad", &wad_bytes(&["D_RUNNIN"])),
+                ("license.txt", b"Do whatever you want"),
+            ]),
+        )
+        .unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.extracted, vec!["maps.wad", "music.wad", "maps.txt"]);
+        assert_eq!(fs::read(mods.join("maps.txt")).unwrap(), description);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].name, "license.txt");
+    }
+
+    #[test]
+    fn rejects_text_files_that_are_too_large() {
+        let dir = temp_dir("large-text");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        let source = dir.join("mapset.zip");
+        fs::write(
+            &source,
+            zip_bytes(&[
+                ("mapset.wad", &wad_bytes(&["MAP01", "THINGS"])),
+                (
+                    "mapset.txt",
+                    &vec![b'x'; idgames::MAX_TEXT_SIZE as usize + 1],
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let report = import(&source, &mods);
+        assert_eq!(report.extracted, vec!["mapset.wad"]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].name, "mapset.txt");
+        assert!(!mods.join("mapset.txt").exists());
+    }
+
+    #[test]
+    fn imports_a_loose_text_file_next_to_its_mod() {
+        let dir = temp_dir("loose-text");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        fs::write(mods.join("MAPSET.wad"), wad_bytes(&["MAP01", "THINGS"])).unwrap();
+        let text = dir.join("mapset.txt");
+        fs::write(&text, "Title : Mapset\n").unwrap();
+
+        let report = import(&text, &mods);
+        assert_eq!(report.error, None);
+        assert_eq!(report.extracted, vec!["MAPSET.txt"]);
+        assert_eq!(
+            fs::read_to_string(mods.join("MAPSET.txt")).unwrap(),
+            "Title : Mapset\n"
+        );
+
+        let report = import(&text, &mods);
+        assert_eq!(report.skipped, vec!["MAPSET.txt"]);
+
+        let orphan = dir.join("other.txt");
+        fs::write(&orphan, "Title : Other\n").unwrap();
+        let report = import(&orphan, &mods);
+        assert!(report.extracted.is_empty());
+        assert_eq!(report.rejected[0].name, "other.txt");
+        assert!(!mods.join("other.txt").exists());
+    }
+
+    #[test]
+    fn keeps_packages_of_archives_with_the_same_prefix() {
+        let dir = temp_dir("versions");
+        let mods = dir.join("mods");
+        fs::create_dir(&mods).unwrap();
+        for (archive, wad) in [
+            ("sunlust-v1.1.zip", "sunlust11.wad"),
+            ("sunlust-v1.2.zip", "sunlust12.wad"),
+        ] {
+            let source = dir.join(archive);
+            fs::write(
+                &source,
+                zip_bytes(&[(wad, &wad_bytes(&["MAP01", "THINGS"]))]),
+            )
+            .unwrap();
+            assert_eq!(import(&source, &mods).packages.len(), 1);
+        }
+
+        let packages = package::load_packages();
+        let versions: Vec<(&str, &[String])> = packages
+            .iter()
+            .filter(|package| package.name.starts_with("sunlust-v1."))
+            .map(|package| (package.name.as_str(), package.files.as_slice()))
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                ("sunlust-v1.1", &["sunlust11.wad".to_string()][..]),
+                ("sunlust-v1.2", &["sunlust12.wad".to_string()][..]),
+            ]
+        );
+    }
+}
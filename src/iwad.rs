@@ -0,0 +1,314 @@
+use md5::{Digest, Md5};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::wad::{self, Wad, WadKind};
+
+/// A known release of a game IWAD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IwadInfo {
+    pub game: &'static str,
+    pub release: &'static str,
+}
+
+/// Known IWAD releases keyed by the MD5 hash of the whole file: (hash, game, release)
+///
+/// Freedoom is not listed. Every release has new hashes, so it is told apart from the
+/// other games by its FREEDOOM lump in `guess_game` and shown without a release
+/// Largest GAMEINFO that is read, it only holds a few settings
+pub const MAX_GAMEINFO_SIZE: u64 = 64 * 1024;
+
+const KNOWN_IWADS: [(&str, &str, &str); 14] = [
+    (
+        "f0cefca49926d00903cf57551d901abe",
+        "Doom (Shareware)",
+        "v1.9",
+    ),
+    ("1cd63c5ddff1bf8ce844237f580e9cf3", "Doom", "v1.9"),
+    (
+        "c4fe9fd920207691a9f493668e0a2083",
+        "The Ultimate Doom",
+        "v1.9",
+    ),
+    (
+        "fb35c4a5a9fd49ec29ab6e900572c524",
+        "The Ultimate Doom",
+        "BFG Edition",
+    ),
+    ("25e1459ca71d321525f84628f45ca8cd", "Doom II", "v1.9"),
+    ("c3bea40570c23e511a7ed3ebcd9865f7", "Doom II", "BFG Edition"),
+    (
+        "4e158d9953c79ccf97bd0663244cc6b6",
+        "Final Doom: TNT Evilution",
+        "v1.9",
+    ),
+    (
+        "1d39e405bf6ee3df69a8d2646c8d5c49",
+        "Final Doom: TNT Evilution",
+        "id Anthology",
+    ),
+    (
+        "75c8cf89566741fa9d22447604053bd7",
+        "Final Doom: The Plutonia Experiment",
+        "v1.9",
+    ),
+    (
+        "3493be7e1e2588bc9c8b31eab2587a04",
+        "Final Doom: The Plutonia Experiment",
+        "id Anthology",
+    ),
+    (
+        "ae779722390ec32fa37b0d361f7d82f8",
+        "Heretic (Shareware)",
+        "v1.2",
+    ),
+    (
+        "66d686b1ed6d35ff103f15dbd30e0341",
+        "Heretic: Shadow of the Serpent Riders",
+        "v1.3",
+    ),
+    ("abb033caf81e26f12a2103e1fa25453f", "Hexen", "v1.1"),
+    ("25485721882b050afa96a56e5758dd52", "Chex Quest", "v1.0"),
+];
+
+/// The result of checking a file that was picked as the base game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IwadIdentity {
+    /// The file's hash matches a known release
+    Verified(IwadInfo),
+    /// The file is an IWAD but its hash is unknown. The game is guessed from its lumps
+    Unverified { game: &'static str },
+    /// The file is a PWAD (a mod), not a game
+    Pwad,
+    /// The file could not be read as a WAD at all
+    Invalid(String),
+}
+
+impl IwadIdentity {
+    /// Builds the text shown in the Settings view for this IWAD
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - String such as "Doom II v1.9 (verified)" or a warning message
+    pub fn describe(&self) -> String {
+        match self {
+            IwadIdentity::Verified(info) => format!("{} {} (verified)", info.game, info.release),
+            IwadIdentity::Unverified { game } => format!("{} (unknown release)", game),
+            IwadIdentity::Pwad => {
+                "Warning: this is a PWAD (a mod), not a game IWAD. Add it to your mods instead."
+                    .to_string()
+            }
+            IwadIdentity::Invalid(e) => format!("Warning: not a valid WAD file ({})", e),
+        }
+    }
+}
+
+/// Function to identify the game and release of an IWAD
+///
+/// #Arguments
+/// - path to the wad file as a string reference
+///
+/// #Returns
+/// - IwadIdentity describing what the file is
+/// - Invalid if the file is missing or corrupt
+pub fn identify_iwad(path: &str) -> IwadIdentity {
+    let parsed = match wad::read_wad(path) {
+        Ok(parsed) => parsed,
+        Err(e) => return IwadIdentity::Invalid(e.to_string()),
+    };
+
+    if parsed.kind == WadKind::Pwad {
+        return IwadIdentity::Pwad;
+    }
+
+    let hash = match hash_file(path) {
+        Ok(hash) => hash,
+        Err(e) => return IwadIdentity::Invalid(e.to_string()),
+    };
+
+    match KNOWN_IWADS.iter().find(|(known, _, _)| *known == hash) {
+        Some((_, game, release)) => IwadIdentity::Verified(IwadInfo { game, release }),
+        None => IwadIdentity::Unverified {
+            game: guess_game(&parsed),
+        },
+    }
+}
+
+/// Calculates the MD5 hash of a file as a lowercase hex string
+pub fn hash_file(path: &str) -> std::io::Result<String> {
+    let mut reader = BufReader::new(File::open(Path::new(path))?);
+    let mut hasher = Md5::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Guesses the game of an unknown IWAD from lumps that are unique to each game.
+/// This catches Freedoom (which changes with every release), modified IWADs and
+/// releases missing from the table.
+fn guess_game(parsed: &Wad) -> &'static str {
+    let has = |name: &str| parsed.lumps.iter().any(|lump| lump.name == name);
+
+    if has("FREEDOOM") {
+        if has("MAP01") {
+            "Freedoom: Phase 2"
+        } else {
+            "Freedoom: Phase 1"
+        }
+    } else if has("WINNOWR") {
+        "Hexen"
+    } else if has("MUS_E1M1") {
+        "Heretic"
+    } else if has("W94_1") {
+        "Chex Quest"
+    } else if has("MAP01") {
+        "Doom II"
+    } else if has("E1M1") {
+        "Doom"
+    } else {
+        "Unknown game"
+    }
+}
+
+/// Reads the IWAD a mod asks for from the text of its GAMEINFO lump, e.g. IWAD = "doom2.wad"
+///
+/// #Arguments
+/// - text of the GAMEINFO lump as a string reference
+///
+/// #Returns
+/// - the lowercase IWAD file name, or None if GAMEINFO does not name one
+pub fn parse_gameinfo_iwad(gameinfo: &str) -> Option<String> {
+    gameinfo.lines().find_map(|line| {
+        let line = line.split("//").next()?;
+        let (key, value) = line.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("IWAD") {
+            return None;
+        }
+        let value = value.trim().trim_matches('"').trim();
+        (!value.is_empty()).then(|| value.to_lowercase())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Writes a WAD with empty lumps of the given names to the system temp folder
+    fn write_wad(name: &str, magic: &[u8; 4], lumps: &[&str]) -> PathBuf {
+        let mut data = Vec::new();
+        data.extend_from_slice(magic);
+        data.extend_from_slice(&(lumps.len() as u32).to_le_bytes());
+        data.extend_from_slice(&12u32.to_le_bytes());
+        for lump in lumps {
+            data.extend_from_slice(&12u32.to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            let mut padded = [0u8; 8];
+            padded[..lump.len()].copy_from_slice(lump.as_bytes());
+            data.extend_from_slice(&padded);
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "imp-porter-iwad-{}-{}.wad",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn identify(path: &Path) -> IwadIdentity {
+        identify_iwad(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn tells_freedoom_phases_apart() {
+        let phase1 = write_wad("freedoom1", b"IWAD", &["FREEDOOM", "E1M1", "THINGS"]);
+        let phase2 = write_wad("freedoom2", b"IWAD", &["FREEDOOM", "MAP01", "THINGS"]);
+
+        assert_eq!(
+            identify(&phase1),
+            IwadIdentity::Unverified {
+                game: "Freedoom: Phase 1"
+            }
+        );
+        assert_eq!(
+            identify(&phase2),
+            IwadIdentity::Unverified {
+                game: "Freedoom: Phase 2"
+            }
+        );
+    }
+
+    #[test]
+    fn guesses_unknown_releases_from_their_lumps() {
+        let cases = [
+            ("doom", vec!["E1M1", "THINGS"], "Doom"),
+            ("doom2", vec!["MAP01", "THINGS"], "Doom II"),
+            ("heretic", vec!["MUS_E1M1", "E1M1"], "Heretic"),
+            ("hexen", vec!["WINNOWR", "MAP01"], "Hexen"),
+            ("chex", vec!["W94_1", "E1M1"], "Chex Quest"),
+            ("unknown", vec!["PLAYPAL"], "Unknown game"),
+        ];
+        for (name, lumps, game) in cases {
+            let path = write_wad(name, b"IWAD", &lumps);
+            assert_eq!(
+                identify(&path),
+                IwadIdentity::Unverified { game },
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn flags_pwads_and_broken_files() {
+        let pwad = write_wad("pwad", b"PWAD", &["MAP01", "THINGS"]);
+        assert_eq!(identify(&pwad), IwadIdentity::Pwad);
+
+        let broken =
+            std::env::temp_dir().join(format!("imp-porter-iwad-broken-{}.wad", std::process::id()));
+        fs::write(&broken, b"IWAD").unwrap();
+        assert!(matches!(identify(&broken), IwadIdentity::Invalid(_)));
+        assert!(matches!(
+            identify(Path::new("/nonexistent/doom2.wad")),
+            IwadIdentity::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn hashes_the_whole_file() {
+        let path =
+            std::env::temp_dir().join(format!("imp-porter-iwad-hash-{}.bin", std::process::id()));
+        fs::write(&path, b"").unwrap();
+        assert_eq!(
+            hash_file(path.to_str().unwrap()).unwrap(),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+    }
+
+    #[test]
+    fn reads_the_iwad_from_gameinfo() {
+        assert_eq!(
+            parse_gameinfo_iwad("LOAD = \"music.pk3\"\nIWAD = \"DOOM2.WAD\" // needs Doom II\n"),
+            Some("doom2.wad".to_string())
+        );
+        assert_eq!(
+            parse_gameinfo_iwad("iwad=tnt.wad"),
+            Some("tnt.wad".to_string())
+        );
+        assert_eq!(
+            parse_gameinfo_iwad("// IWAD = doom.wad\nSTARTUPTITLE = \"x\""),
+            None
+        );
+        assert_eq!(parse_gameinfo_iwad("IWAD = \"\""), None);
+    }
+}
@@ -0,0 +1,885 @@
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::compat::CompatLevel;
+use crate::idgames::{self, ModInfo};
+use crate::import::ImportReport;
+use crate::package::{self, Package};
+use crate::utility::{self, ModEntry, ModKind};
+use crate::{iwad, paths};
+
+/// Version of the database schema written by this build. Bump it whenever a table
+/// changes and add the upgrade step to `migrate`.
+const LIBRARY_VERSION: i32 = 1;
+
+/// How long to wait for the import thread or another imp-orter to finish writing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A mod file with everything the library remembers about it
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryFile {
+    /// File name in the mods directory
+    pub name: String,
+    /// What was read from the file the last time it changed
+    #[serde(flatten)]
+    pub entry: ModEntry,
+    /// Size in bytes
+    pub size: u64,
+    /// MD5 of the contents as a lowercase hex string
+    pub hash: String,
+    /// File name of the archive the file was imported from
+    pub source_archive: Option<String>,
+    /// Seconds since the Unix epoch when the file was imported or first found
+    pub imported_at: i64,
+    /// Number of times the file was launched
+    pub play_count: u32,
+    /// Seconds since the Unix epoch when the file was last launched
+    pub last_played: Option<i64>,
+    /// Tags, rating, notes, favorite and status set by the user
+    #[serde(flatten)]
+    pub user: UserData,
+}
+
+/// How far the user got with a mod
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayStatus {
+    InProgress,
+    Completed,
+}
+
+impl PlayStatus {
+    /// Label used in the UI and the library
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlayStatus::InProgress => "in progress",
+            PlayStatus::Completed => "completed",
+        }
+    }
+
+    /// Function to read a status from its label
+    ///
+    /// #Arguments
+    /// - the label, case-insensitive, with a space, '-' or '_' between words
+    ///
+    /// #Returns
+    /// - the PlayStatus, or None if the label is unknown
+    pub fn from_label(label: &str) -> Option<PlayStatus> {
+        match label
+            .trim()
+            .to_lowercase()
+            .replace(['-', '_'], " ")
+            .as_str()
+        {
+            "in progress" => Some(PlayStatus::InProgress),
+            "completed" => Some(PlayStatus::Completed),
+            _ => None,
+        }
+    }
+}
+
+/// What the user wrote down about a mod. It is stored by the file's hash, so it stays
+/// with the mod when the file is renamed, moved to another folder or removed and imported again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UserData {
+    pub tags: Vec<String>,
+    /// 1 to 5
+    pub rating: Option<u8>,
+    pub notes: String,
+    pub favorite: bool,
+    pub status: Option<PlayStatus>,
+}
+
+impl UserData {
+    /// True when nothing was set, such entries are not stored
+    fn is_empty(&self) -> bool {
+        *self == UserData::default()
+    }
+}
+
+/// The user data of one mod as written by `Library::export_user_data`
+#[derive(Debug, Clone, Serialize)]
+pub struct UserDataExport {
+    /// File name the mod had when its user data was last changed
+    pub name: String,
+    /// MD5 of the mod's contents
+    pub hash: String,
+    /// Where the mod is now, empty if it is no longer in the library
+    pub paths: Vec<String>,
+    #[serde(flatten)]
+    pub user: UserData,
+}
+
+/// The library database kept in the data directory
+pub struct Library {
+    connection: Connection,
+}
+
+/// Function to get the path of library.db
+///
+/// #Arguments
+///  - none
+///
+/// #Returns
+/// - PathBuf inside the data directory
+pub fn get_library_path() -> PathBuf {
+    paths::get().data_dir.join("library.db")
+}
+
+impl Library {
+    /// Function to open the library, creating or upgrading it if needed
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - the Library
+    /// - an error message if the database can't be opened
+    pub fn open() -> Result<Library, String> {
+        let path = get_library_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let connection = Connection::open(&path).map_err(|e| e.to_string())?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| connection.pragma_update(None, "foreign_keys", true))
+            .map_err(|e| e.to_string())?;
+
+        let mut library = Library { connection };
+        library.migrate().map_err(|e| e.to_string())?;
+        Ok(library)
+    }
+
+    /// Creates the tables, or upgrades them from an older LIBRARY_VERSION
+    fn migrate(&mut self) -> rusqlite::Result<()> {
+        let version: i32 = self
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        if version > LIBRARY_VERSION {
+            eprintln!(
+                "Library version {} is newer than this build supports ({})",
+                version, LIBRARY_VERSION
+            );
+            return Ok(());
+        }
+
+        if version < 1 {
+            // size and modified are NULL until the file is scanned, which forces a re-parse.
+            // info_modified is the modification time of the idgames text file next to the
+            // mod, NULL when there is none.
+            // user data is keyed by the hash of the file so it survives renames
+            self.connection.execute_batch(
+                "CREATE TABLE IF NOT EXISTS files (
+                    path TEXT PRIMARY KEY,
+                    kind TEXT NOT NULL,
+                    size INTEGER,
+                    modified INTEGER,
+                    hash TEXT,
+                    maps TEXT NOT NULL DEFAULT '[]',
+                    required_iwad TEXT,
+                    info TEXT,
+                    info_modified INTEGER,
+                    compat TEXT,
+                    source_archive TEXT,
+                    imported_at INTEGER NOT NULL,
+                    play_count INTEGER NOT NULL DEFAULT 0,
+                    last_played INTEGER
+                );
+                CREATE TABLE IF NOT EXISTS lumps (
+                    path TEXT NOT NULL REFERENCES files(path) ON DELETE CASCADE,
+                    position INTEGER NOT NULL,
+                    name TEXT NOT NULL,
+                    offset INTEGER NOT NULL,
+                    size INTEGER NOT NULL,
+                    PRIMARY KEY (path, position)
+                );
+                CREATE TABLE IF NOT EXISTS user_data (
+                    hash TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    rating INTEGER,
+                    notes TEXT NOT NULL DEFAULT '',
+                    favorite INTEGER NOT NULL DEFAULT 0,
+                    status TEXT
+                );
+                CREATE TABLE IF NOT EXISTS user_tags (
+                    hash TEXT NOT NULL REFERENCES user_data(hash) ON DELETE CASCADE,
+                    tag TEXT NOT NULL,
+                    PRIMARY KEY (hash, tag)
+                );",
+            )?;
+        }
+
+        self.connection
+            .pragma_update(None, "user_version", LIBRARY_VERSION)
+    }
+
+    /// Function to bring the library up to date with a mods directory and list its files
+    ///
+    /// #Arguments
+    /// - path to the mods directory as a string reference
+    ///
+    /// #Returns
+    /// - Vector of LibraryFiles for every valid mod file in the folder and its subfolders
+    /// - an error message if the database can't be updated
+    ///
+    /// Only files whose size or modification time changed since the last scan are read
+    /// and hashed again. The idgames fields are read again when the mod's text file was
+    /// added, changed or removed. Files that were removed from the folder are removed from the
+    /// library. Files that are not valid (truncated, corrupt or mislabeled) are skipped.
+    pub fn scan(&mut self, folder_path: &str) -> Result<Vec<LibraryFile>, String> {
+        let packages = package::load_packages();
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+
+        let mut files = Vec::new();
+        let mut seen = HashSet::new();
+        let mut text_files = TextFiles::default();
+        for path in utility::find_mod_files(folder_path) {
+            let path_str = path.to_string_lossy().into_owned();
+            let info_modified = text_files.modified_for(&path);
+            match scan_file(&transaction, &path, &path_str, info_modified, &packages) {
+                Ok(mut file) => {
+                    // the category follows the folder, it is not stored
+                    file.entry.category = utility::category_of(folder_path, &path);
+                    seen.insert(path_str);
+                    files.push(file);
+                }
+                Err(e) => eprintln!("Skipping invalid mod '{}': {}", path_str, e),
+            }
+        }
+
+        // forget files that were deleted or moved out of the folder
+        let stored = stored_paths(&transaction, folder_path, false).map_err(|e| e.to_string())?;
+        for path in stored {
+            if !seen.contains(&path) {
+                transaction
+                    .execute("DELETE FROM files WHERE path = ?1", [&path])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        transaction.commit().map_err(|e| e.to_string())?;
+        Ok(files)
+    }
+
+    /// Function to list what the library knows about a mods directory without reading it
+    ///
+    /// #Arguments
+    /// - path to the mods directory as a string reference
+    ///
+    /// #Returns
+    /// - Vector of LibraryFiles as of the last `scan`
+    /// - an error message if the database can't be read
+    pub fn files(&self, folder_path: &str) -> Result<Vec<LibraryFile>, String> {
+        let mut files = Vec::new();
+        for path in stored_paths(&self.connection, folder_path, true).map_err(|e| e.to_string())? {
+            let mut file = load_file(&self.connection, &path).map_err(|e| e.to_string())?;
+            file.entry.category = utility::category_of(folder_path, Path::new(&path));
+            files.push(file);
+        }
+        Ok(files)
+    }
+
+    /// Function to change the tags, rating, notes, favorite and status of a mod
+    ///
+    /// #Arguments
+    /// - path of the mod file, as listed by `scan`
+    /// - the new UserData, replacing what was stored
+    ///
+    /// #Returns
+    /// - an error message if the file is not in the library or the database can't be updated
+    ///
+    /// Tags are trimmed and empty or duplicate tags are dropped. A rating outside 1 to 5
+    /// is stored as no rating.
+    pub fn set_user_data(&mut self, path: &str, user: &UserData) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+
+        let hash: String = transaction
+            .query_row(
+                "SELECT hash FROM files WHERE path = ?1 AND hash IS NOT NULL",
+                [path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("{} is not in the library", path))?;
+
+        let mut user = user.clone();
+        user.rating = user.rating.filter(|rating| (1..=5).contains(rating));
+        let mut tags: Vec<String> = Vec::new();
+        for tag in user.tags.iter().map(|tag| tag.trim()) {
+            if !tag.is_empty() && !tags.iter().any(|kept| kept.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_string());
+            }
+        }
+        user.tags = tags;
+
+        // the tags are removed with the row
+        transaction
+            .execute("DELETE FROM user_data WHERE hash = ?1", [&hash])
+            .map_err(|e| e.to_string())?;
+
+        if !user.is_empty() {
+            let name = Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            transaction
+                .execute(
+                    "INSERT INTO user_data (hash, name, rating, notes, favorite, status)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        hash,
+                        name,
+                        user.rating,
+                        user.notes,
+                        user.favorite,
+                        user.status.map(|status| status.as_str())
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            for tag in &user.tags {
+                transaction
+                    .execute(
+                        "INSERT INTO user_tags (hash, tag) VALUES (?1, ?2)",
+                        params![hash, tag],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    /// Function to list the user data of every mod, including mods that are no longer
+    /// in the mods directory
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - Vector of UserDataExports sorted by name
+    /// - an error message if the database can't be read
+    pub fn export_user_data(&self) -> Result<Vec<UserDataExport>, String> {
+        let stored: Vec<(String, String)> = self
+            .connection
+            .prepare("SELECT hash, name FROM user_data ORDER BY name COLLATE NOCASE")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<Vec<(String, String)>>>()
+            })
+            .map_err(|e| e.to_string())?;
+
+        let mut exports = Vec::new();
+        for (hash, name) in stored {
+            let paths = self
+                .connection
+                .prepare_cached("SELECT path FROM files WHERE hash = ?1 ORDER BY path")
+                .and_then(|mut statement| {
+                    statement
+                        .query_map([&hash], |row| row.get(0))?
+                        .collect::<rusqlite::Result<Vec<String>>>()
+                })
+                .map_err(|e| e.to_string())?;
+            let user = load_user_data(&self.connection, &hash).map_err(|e| e.to_string())?;
+            exports.push(UserDataExport {
+                name,
+                hash,
+                paths,
+                user,
+            });
+        }
+        Ok(exports)
+    }
+
+    /// Function to remember the archive and date of the files written by an import
+    ///
+    /// #Arguments
+    /// - the mods directory the files were written to
+    /// - the ImportReport of the import
+    ///
+    /// #Returns
+    /// - an error message if the database can't be updated
+    ///
+    /// The files are read on the next scan
+    pub fn record_import(&mut self, target_dir: &str, report: &ImportReport) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        let imported_at = now();
+
+        for name in &report.extracted {
+            let path = Path::new(target_dir).join(name);
+            let Some(kind) = ModKind::from_path(&path) else {
+                continue;
+            };
+            transaction
+                .execute(
+                    "INSERT INTO files (path, kind, source_archive, imported_at)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (path) DO UPDATE SET
+                        source_archive = excluded.source_archive,
+                        imported_at = excluded.imported_at,
+                        size = NULL,
+                        modified = NULL",
+                    params![
+                        path.to_string_lossy(),
+                        kind.as_str(),
+                        source_archive_for(name, &report.packages),
+                        imported_at
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    /// Function to count a launch for each mod file in a load order
+    ///
+    /// #Arguments
+    /// - paths of the mod files that were launched
+    ///
+    /// #Returns
+    /// - an error message if the database can't be updated
+    pub fn record_play(&mut self, mod_paths: &[String]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        let played_at = now();
+
+        for path in mod_paths {
+            transaction
+                .execute(
+                    "UPDATE files SET play_count = play_count + 1, last_played = ?1
+                     WHERE path = ?2",
+                    params![played_at, path],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+
+        transaction.commit().map_err(|e| e.to_string())
+    }
+}
+
+/// Reads a file from the library, parsing and storing it first if it is new or changed
+fn scan_file(
+    transaction: &Transaction,
+    path: &Path,
+    path_str: &str,
+    info_modified: Option<i64>,
+    packages: &[Package],
+) -> Result<LibraryFile, String> {
+    let kind = ModKind::from_path(path).ok_or("not a mod file")?;
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let size = metadata.len() as i64;
+    let modified = modified_nanos(&metadata).unwrap_or_default();
+
+    let stored: Option<(Option<i64>, Option<i64>, Option<i64>)> = transaction
+        .query_row(
+            "SELECT size, modified, info_modified FROM files WHERE path = ?1",
+            [path_str],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    if stored.is_some_and(|(stored_size, stored_modified, stored_info_modified)| {
+        stored_size == Some(size)
+            && stored_modified == Some(modified)
+            && stored_info_modified != info_modified
+    }) {
+        // only the text file changed, the mod itself does not need to be read again
+        let info = idgames::read_mod_info(path).and_then(|info| serde_json::to_string(&info).ok());
+        transaction
+            .execute(
+                "UPDATE files SET info = ?1, info_modified = ?2 WHERE path = ?3",
+                params![info, info_modified, path_str],
+            )
+            .map_err(|e| e.to_string())?;
+    } else if stored.map(|(size, modified, _)| (size, modified))
+        != Some((Some(size), Some(modified)))
+    {
+        let entry = utility::read_mod_entry(path_str, kind)?;
+        let hash = iwad::hash_file(path_str).map_err(|e| e.to_string())?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        store_entry(
+            transaction,
+            &entry,
+            size,
+            modified,
+            info_modified,
+            &hash,
+            source_archive_for(&name, packages),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    load_file(transaction, path_str).map_err(|e| e.to_string())
+}
+
+/// Modification time of a file in nanoseconds since the Unix epoch
+fn modified_nanos(metadata: &fs::Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos() as i64)
+}
+
+/// The text files of each folder of a scan, listed once per folder instead of once per mod
+#[derive(Default)]
+struct TextFiles(HashMap<PathBuf, Vec<PathBuf>>);
+
+impl TextFiles {
+    /// Modification time of the text file kept next to a mod, found the same way as
+    /// `idgames::text_file_for`. None if the mod has no text file
+    fn modified_for(&mut self, mod_path: &Path) -> Option<i64> {
+        let folder = mod_path.parent()?;
+        let texts = self.0.entry(folder.to_path_buf()).or_insert_with(|| {
+            fs::read_dir(folder)
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .map(|entry| entry.path())
+                        .filter(|path| {
+                            path.extension()
+                                .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+                                && path.is_file()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+
+        let stem = mod_path.file_stem()?.to_string_lossy().to_lowercase();
+        let text = texts.iter().find(|text| {
+            text.file_stem()
+                .is_some_and(|text_stem| text_stem.to_string_lossy().to_lowercase() == stem)
+        })?;
+        modified_nanos(&fs::metadata(text).ok()?)
+    }
+}
+
+/// Writes what was read from a file, keeping its import date, play count and tags
+fn store_entry(
+    transaction: &Transaction,
+    entry: &ModEntry,
+    size: i64,
+    modified: i64,
+    info_modified: Option<i64>,
+    hash: &str,
+    source_archive: Option<String>,
+) -> rusqlite::Result<()> {
+    let maps = serde_json::to_string(&entry.maps).unwrap_or_else(|_| "[]".to_string());
+    let info = entry
+        .info
+        .as_ref()
+        .and_then(|info| serde_json::to_string(info).ok());
+
+    transaction.execute(
+        "INSERT INTO files
+            (path, kind, size, modified, hash, maps, required_iwad, info, info_modified, compat,
+             source_archive, imported_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+         ON CONFLICT (path) DO UPDATE SET
+            kind = excluded.kind,
+            size = excluded.size,
+            modified = excluded.modified,
+            hash = excluded.hash,
+            maps = excluded.maps,
+            required_iwad = excluded.required_iwad,
+            info = excluded.info,
+            info_modified = excluded.info_modified,
+            compat = excluded.compat,
+            source_archive = COALESCE(files.source_archive, excluded.source_archive)",
+        params![
+            entry.path,
+            entry.kind.as_str(),
+            size,
+            modified,
+            hash,
+            maps,
+            entry.required_iwad,
+            info,
+            info_modified,
+            entry.compat.as_str(),
+            source_archive,
+            now()
+        ],
+    )?;
+
+    transaction.execute("DELETE FROM lumps WHERE path = ?1", [&entry.path])?;
+    let mut insert = transaction.prepare_cached(
+        "INSERT INTO lumps (path, position, name, offset, size) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for (position, lump) in entry.lumps.iter().enumerate() {
+        insert.execute(params![
+            entry.path,
+            position as i64,
+            lump.name,
+            lump.offset,
+            lump.size
+        ])?;
+    }
+
+    Ok(())
+}
+
+/// Paths of the files stored for a folder and its subfolders
+///
+/// Files recorded by an import but not scanned yet are left out when only_scanned is true
+fn stored_paths(
+    connection: &Connection,
+    folder_path: &str,
+    only_scanned: bool,
+) -> rusqlite::Result<Vec<String>> {
+    let sql = if only_scanned {
+        "SELECT path FROM files WHERE size IS NOT NULL"
+    } else {
+        "SELECT path FROM files"
+    };
+    let paths = connection
+        .prepare(sql)?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    Ok(paths
+        .into_iter()
+        .filter(|path| Path::new(path).starts_with(folder_path))
+        .collect())
+}
+
+/// Reads what is stored for a file, except its lumps, which nothing after the scan reads
+fn load_file(connection: &Connection, path: &str) -> rusqlite::Result<LibraryFile> {
+    let mut file = connection.query_row(
+        "SELECT size, hash, maps, required_iwad, info, source_archive, imported_at,
+                play_count, last_played, compat
+         FROM files WHERE path = ?1",
+        [path],
+        |row| {
+            let maps: String = row.get(2)?;
+            let info: Option<String> = row.get(4)?;
+            Ok(LibraryFile {
+                name: Path::new(path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                entry: ModEntry {
+                    path: path.to_string(),
+                    kind: ModKind::from_path(path).unwrap_or(ModKind::Wad),
+                    maps: serde_json::from_str(&maps).unwrap_or_default(),
+                    required_iwad: row.get(3)?,
+                    info: info.and_then(|info| serde_json::from_str::<ModInfo>(&info).ok()),
+                    compat: row
+                        .get::<_, Option<String>>(9)?
+                        .and_then(|compat| CompatLevel::from_label(&compat))
+                        .unwrap_or(CompatLevel::Vanilla),
+                    category: String::new(),
+                    lumps: Vec::new(),
+                },
+                size: row.get::<_, i64>(0)? as u64,
+                hash: row.get(1)?,
+                source_archive: row.get(5)?,
+                imported_at: row.get(6)?,
+                play_count: row.get(7)?,
+                last_played: row.get(8)?,
+                user: UserData::default(),
+            })
+        },
+    )?;
+
+    file.user = load_user_data(connection, &file.hash)?;
+    Ok(file)
+}
+
+/// Reads the user data stored for a file's hash, empty if there is none
+fn load_user_data(connection: &Connection, hash: &str) -> rusqlite::Result<UserData> {
+    let mut user = connection
+        .prepare_cached("SELECT rating, notes, favorite, status FROM user_data WHERE hash = ?1")?
+        .query_row([hash], |row| {
+            Ok(UserData {
+                tags: Vec::new(),
+                rating: row.get(0)?,
+                notes: row.get(1)?,
+                favorite: row.get(2)?,
+                status: row
+                    .get::<_, Option<String>>(3)?
+                    .and_then(|status| PlayStatus::from_label(&status)),
+            })
+        })
+        .optional()?
+        .unwrap_or_default();
+
+    user.tags = connection
+        .prepare_cached("SELECT tag FROM user_tags WHERE hash = ?1 ORDER BY tag")?
+        .query_map([hash], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    Ok(user)
+}
+
+/// Finds the archive a mod file was extracted from in the recorded packages
+fn source_archive_for(name: &str, packages: &[Package]) -> Option<String> {
+    packages
+        .iter()
+        .find(|package| package.files.iter().any(|file| file == name))
+        .map(|package| package.source_archive.clone())
+}
+
+/// Seconds since the Unix epoch
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mods folder of its own under the system temp folder with one map WAD in it
+    fn mods_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "imp-porter-library-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut wad = Vec::new();
+        wad.extend_from_slice(b"PWAD");
+        wad.extend_from_slice(&2u32.to_le_bytes());
+        wad.extend_from_slice(&12u32.to_le_bytes());
+        for lump in [b"MAP01\0\0\0", b"THINGS\0\0"] {
+            wad.extend_from_slice(&12u32.to_le_bytes());
+            wad.extend_from_slice(&0u32.to_le_bytes());
+            wad.extend_from_slice(lump);
+        }
+        fs::write(dir.join("mapset.wad"), wad).unwrap();
+        dir
+    }
+
+    fn title(library: &mut Library, dir: &Path) -> Option<String> {
+        let files = library.scan(&dir.to_string_lossy()).unwrap();
+        assert_eq!(files.len(), 1);
+        files[0].entry.info.as_ref()?.title.clone()
+    }
+
+    #[test]
+    fn scans_a_mods_folder() {
+        let dir = mods_dir("scan");
+        fs::create_dir(dir.join("megawads")).unwrap();
+        fs::copy(dir.join("mapset.wad"), dir.join("megawads/other.wad")).unwrap();
+        let mut library = Library::open().unwrap();
+
+        let mut files = library.scan(&dir.to_string_lossy()).unwrap();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name, "mapset.wad");
+        assert_eq!(files[0].entry.maps, vec!["MAP01"]);
+        assert_eq!(files[0].entry.category, "");
+        assert_eq!(files[1].entry.category, "megawads");
+        assert_eq!(files[0].hash, files[1].hash);
+
+        // removed files are forgotten
+        fs::remove_file(dir.join("megawads/other.wad")).unwrap();
+        assert_eq!(library.scan(&dir.to_string_lossy()).unwrap().len(), 1);
+        assert_eq!(library.files(&dir.to_string_lossy()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reads_the_text_file_again_when_it_changes() {
+        let dir = mods_dir("text");
+        let mut library = Library::open().unwrap();
+        assert_eq!(title(&mut library, &dir), None);
+
+        fs::write(dir.join("MAPSET.txt"), "Title : First\n").unwrap();
+        assert_eq!(title(&mut library, &dir).as_deref(), Some("First"));
+
+        // moved forward so the change is seen where file times are coarse
+        fs::write(dir.join("MAPSET.txt"), "Title : Second edition\n").unwrap();
+        let text = fs::File::options()
+            .write(true)
+            .open(dir.join("MAPSET.txt"))
+            .unwrap();
+        text.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(title(&mut library, &dir).as_deref(), Some("Second edition"));
+
+        fs::remove_file(dir.join("MAPSET.txt")).unwrap();
+        assert_eq!(title(&mut library, &dir), None);
+    }
+
+    #[test]
+    fn keeps_user_data_with_the_contents_of_the_file() {
+        let dir = mods_dir("user-data");
+        // bytes after the directory make the hash differ from the other tests' WADs
+        let mut contents = fs::read(dir.join("mapset.wad")).unwrap();
+        contents.extend_from_slice(b"user data");
+        fs::write(dir.join("mapset.wad"), contents).unwrap();
+        let folder = dir.to_string_lossy().into_owned();
+        let mut library = Library::open().unwrap();
+        library.scan(&folder).unwrap();
+
+        let user = UserData {
+            tags: vec![
+                " slaughter ".to_string(),
+                "Slaughter".to_string(),
+                "".to_string(),
+            ],
+            rating: Some(9),
+            notes: "MAP27 is hard".to_string(),
+            favorite: true,
+            status: Some(PlayStatus::InProgress),
+        };
+        library
+            .set_user_data(&dir.join("mapset.wad").to_string_lossy(), &user)
+            .unwrap();
+
+        // renaming the file keeps what the user wrote
+        fs::rename(dir.join("mapset.wad"), dir.join("renamed.wad")).unwrap();
+        let files = library.scan(&folder).unwrap();
+        assert_eq!(
+            files[0].user,
+            UserData {
+                tags: vec!["slaughter".to_string()],
+                rating: None,
+                ..user
+            }
+        );
+
+        let exported = library.export_user_data().unwrap();
+        let export = exported
+            .iter()
+            .find(|export| export.hash == files[0].hash)
+            .unwrap();
+        assert_eq!(export.name, "mapset.wad");
+        assert_eq!(export.paths, vec![files[0].entry.path.clone()]);
+
+        // clearing everything removes the entry
+        library
+            .set_user_data(&files[0].entry.path, &UserData::default())
+            .unwrap();
+        assert!(library
+            .export_user_data()
+            .unwrap()
+            .iter()
+            .all(|export| export.hash != files[0].hash));
+        assert!(library
+            .set_user_data(
+                &dir.join("missing.wad").to_string_lossy(),
+                &UserData::default()
+            )
+            .is_err());
+    }
+}
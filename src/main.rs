@@ -1,75 +1,1291 @@
 slint::include_modules!();
-use slint::{Model, ModelRc, SharedString, VecModel};
+use clap::Parser;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use slint::winit_030::{winit::event::WindowEvent, EventResult, WinitWindowAccessor};
+use slint::{Model, ModelRc, SharedString, VecModel, Weak};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
 
+mod archive;
+mod cli;
 mod commands;
+mod compat;
 mod config_functions;
+mod idgames;
+mod import;
+mod iwad;
+mod library;
+mod package;
+mod paths;
+mod profiles;
+mod query;
+mod saves;
+mod source_port;
 mod utility;
+mod wad;
+
+/// How long the mods directory has to stay unchanged before it is rescanned, so copying
+/// or extracting many files causes a single rescan
+const REFRESH_DELAY: Duration = Duration::from_millis(300);
+
+/// Value of a Play view filter that lets every file through
+const ALL_FILTER: &str = "All";
+
+thread_local! {
+    /// What the library knows about the mods directory, read by `reload_library` and
+    /// filtered by `refresh_mod_tree`, so searching does not read the database or config
+    static LIBRARY_FILES: RefCell<LoadedLibrary> = RefCell::default();
+}
+
+/// Library files of the mods directory with the per-mod settings from the config
+#[derive(Default)]
+struct LoadedLibrary {
+    files: Vec<library::LibraryFile>,
+    mods: BTreeMap<String, config_functions::ModSettings>,
+}
+
+/// Converts the scanned mod entries into the ModFile structs used by the Slint UI
+///
+/// #Arguments
+/// - the ModEntries with the user's tags, rating, notes, favorite and status for each
+///
+/// #Returns
+/// - Vector of ModFiles including each file's map list, summary, category, idgames text
+///   file fields and user data, in the same order as the entries
+fn to_mod_files(
+    entries: impl IntoIterator<Item = (utility::ModEntry, library::UserData)>,
+) -> Vec<ModFile> {
+    entries
+        .into_iter()
+        .map(|(entry, user)| ModFile {
+            tags: SharedString::from(user.tags.join(", ")),
+            rating: user.rating.unwrap_or_default() as i32,
+            notes: SharedString::from(user.notes),
+            favorite: user.favorite,
+            status: SharedString::from(
+                user.status
+                    .map(|status| status.as_str())
+                    .unwrap_or_default(),
+            ),
+            name: Path::new(&entry.path)
+                .file_name()
+                .map(|name| SharedString::from(name.to_string_lossy().as_ref()))
+                .unwrap_or_default(),
+            category: SharedString::from(entry.category),
+            path: SharedString::from(entry.path),
+            kind: SharedString::from(entry.kind.as_str()),
+            required_iwad: SharedString::from(entry.required_iwad.unwrap_or_default()),
+            map_summary: SharedString::from(wad::describe_maps(&entry.maps)),
+            title: SharedString::from(
+                entry
+                    .info
+                    .as_ref()
+                    .and_then(|info| info.title.clone())
+                    .unwrap_or_default(),
+            ),
+            info: ModelRc::new(VecModel::from(
+                entry
+                    .info
+                    .map(|info| info.fields())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(label, value)| InfoField {
+                        label: SharedString::from(label),
+                        value: SharedString::from(value),
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+            maps: ModelRc::new(VecModel::from(
+                entry
+                    .maps
+                    .into_iter()
+                    .map(SharedString::from)
+                    .collect::<Vec<_>>(),
+            )),
+        })
+        .collect()
+}
+
+/// Replaces the load order in the UI and refreshes the maps that can be warped to
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - Vector of ModFiles in the order they will be loaded
+///
+/// #Returns
+/// - nothing
+///
+/// The map list is every map from every file in the load order, without duplicates.
+/// The start map is cleared if it no longer exists in the load order.
+fn set_load_order(app: &MenuApp, files: Vec<ModFile>) {
+    let mut maps: Vec<SharedString> = Vec::new();
+    for file in &files {
+        for map in file.maps.iter() {
+            if !maps.contains(&map) {
+                maps.push(map);
+            }
+        }
+    }
+
+    if !maps.contains(&app.get_startMap()) {
+        app.set_startMap(SharedString::new());
+    }
+
+    app.set_selectedMaps(ModelRc::new(VecModel::from(maps)));
+    app.set_loadOrder(ModelRc::new(VecModel::from(files)));
+}
+
+/// Updates the mod list with the result of a scan of the mods directory
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - the mod files from `utility::get_mod_files_in_folder`
+///
+/// #Returns
+/// - nothing
+fn show_mod_files(app: &MenuApp, mod_map: HashMap<String, utility::ModEntry>) {
+    // user data is shown from the rows of the mod tree, which read it from the library
+    let mod_files = sorted_by_name(to_mod_files(
+        mod_map
+            .into_values()
+            .map(|entry| (entry, library::UserData::default())),
+    ));
+    app.set_mod_files(ModelRc::new(VecModel::from(mod_files)));
+    reload_library(app);
+    refresh_packages(app);
+    refresh_presets(app);
+}
+
+/// Path of a mod file relative to the mods directory, the key of its settings, saves,
+/// config profile and package entries
+fn mod_key(file: &ModFile) -> String {
+    utility::mod_key(&file.category, &file.name)
+}
+
+/// Sorts ModFiles by file name, so the order does not change between scans
+fn sorted_by_name(mut mod_files: Vec<ModFile>) -> Vec<ModFile> {
+    mod_files.sort_by_key(|file| file.name.to_lowercase());
+    mod_files
+}
+
+/// Reads the search text, sort order and filters of the Play view
+///
+/// #Arguments
+/// - the MenuApp to read the filter from
+///
+/// #Returns
+/// - the ModQuery, "All" and unknown values turn a filter off
+fn mod_query(app: &MenuApp) -> query::ModQuery {
+    let filter = app.get_modFilter();
+    let chosen = |value: &SharedString| (value.as_str() != ALL_FILTER).then(|| value.to_string());
+
+    query::ModQuery {
+        search: filter.search.to_string(),
+        kind: chosen(&filter.kind).and_then(|kind| utility::ModKind::from_label(&kind)),
+        iwad: chosen(&filter.iwad),
+        tag: chosen(&filter.tag),
+        compat: chosen(&filter.compat).and_then(|compat| compat::CompatLevel::from_label(&compat)),
+        sort: query::SortKey::from_label(&filter.sort).unwrap_or_default(),
+    }
+}
+
+/// Fills in the values the sort order and filters of the Play view can be set to
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - FilterOptions from the files in the library
+///
+/// #Returns
+/// - nothing
+///
+/// A filter is turned off when its value is no longer used by any file
+fn set_filter_options(app: &MenuApp, options: query::FilterOptions) {
+    let with_all = |values: Vec<String>| -> Vec<SharedString> {
+        std::iter::once(ALL_FILTER.to_string())
+            .chain(values)
+            .map(SharedString::from)
+            .collect()
+    };
+    let kinds = with_all(
+        options
+            .kinds
+            .iter()
+            .map(|kind| kind.as_str().to_string())
+            .collect(),
+    );
+    let iwads = with_all(options.iwads);
+    let tags = with_all(options.tags);
+    let compat = with_all(
+        options
+            .compat
+            .iter()
+            .map(|level| level.as_str().to_string())
+            .collect(),
+    );
+
+    let mut filter = app.get_modFilter();
+    for (value, values) in [
+        (&mut filter.kind, &kinds),
+        (&mut filter.iwad, &iwads),
+        (&mut filter.tag, &tags),
+        (&mut filter.compat, &compat),
+    ] {
+        if !values.contains(value) {
+            *value = SharedString::from(ALL_FILTER);
+        }
+    }
+    app.set_modFilter(filter);
+
+    let sorts: Vec<SharedString> = query::SortKey::ALL
+        .iter()
+        .map(|key| SharedString::from(key.label()))
+        .collect();
+    app.set_sortOptions(ModelRc::new(VecModel::from(sorts)));
+    app.set_kindOptions(ModelRc::new(VecModel::from(kinds)));
+    app.set_iwadOptions(ModelRc::new(VecModel::from(iwads)));
+    app.set_tagOptions(ModelRc::new(VecModel::from(tags)));
+    app.set_compatOptions(ModelRc::new(VecModel::from(compat)));
+}
+
+/// Rebuilds the folder tree of the Play view from the files loaded by `reload_library`
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+///
+/// Only files matching the search text and filters are shown, in the chosen order.
+/// Folders come before files and are sorted by name. Folders that were collapsed
+/// stay collapsed and the rows inside them are left out.
+fn refresh_mod_tree(app: &MenuApp) {
+    let collapsed: HashSet<String> = app
+        .get_modTree()
+        .iter()
+        .filter(|row| row.is_folder && !row.expanded)
+        .map(|row| row.folder.to_string())
+        .collect();
+
+    let query = mod_query(app);
+    let matching = LIBRARY_FILES.with(|loaded| {
+        let loaded = loaded.borrow();
+        query.run(&loaded.files, &loaded.mods)
+    });
+    let mod_files = to_mod_files(matching.into_iter().map(|file| (file.entry, file.user)));
+
+    // every folder that holds a mod file, directly or in one of its subfolders
+    let mut folders = BTreeSet::new();
+    for file in &mod_files {
+        let mut folder = file.category.as_str();
+        while !folder.is_empty() {
+            folders.insert(folder.to_string());
+            folder = parent_folder(folder);
+        }
+    }
+
+    let mut rows = Vec::new();
+    add_tree_rows(&mut rows, "", 0, &folders, &mod_files, &collapsed);
+    app.set_modTree(ModelRc::new(VecModel::from(rows)));
+}
+
+/// Reads the library files of the mods directory and the per-mod settings again, then
+/// rebuilds the filter options and the mod tree from them
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+fn reload_library(app: &MenuApp) {
+    let mods_directory = app.get_modDirectory();
+    let files = match library::Library::open().and_then(|library| library.files(&mods_directory)) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Failed to read the library: {}", e);
+            Vec::new()
+        }
+    };
+    let mods = config_functions::load_config().mods;
+    set_filter_options(app, query::filter_options(&files, &mods));
+    LIBRARY_FILES.with(|loaded| *loaded.borrow_mut() = LoadedLibrary { files, mods });
+    refresh_mod_tree(app);
+}
+
+/// Adds the subfolders and mod files of one folder to the mod tree
+fn add_tree_rows(
+    rows: &mut Vec<ModTreeRow>,
+    folder: &str,
+    depth: i32,
+    folders: &BTreeSet<String>,
+    mod_files: &[ModFile],
+    collapsed: &HashSet<String>,
+) {
+    let mut subfolders: Vec<&String> = folders
+        .iter()
+        .filter(|subfolder| parent_folder(subfolder) == folder)
+        .collect();
+    subfolders.sort_by_key(|subfolder| subfolder.to_lowercase());
+
+    for subfolder in subfolders {
+        let expanded = !collapsed.contains(subfolder);
+        let prefix = format!("{}/", subfolder);
+        rows.push(ModTreeRow {
+            is_folder: true,
+            folder: SharedString::from(subfolder.as_str()),
+            label: SharedString::from(subfolder.rsplit('/').next().unwrap_or(subfolder)),
+            depth,
+            expanded,
+            file_count: mod_files
+                .iter()
+                .filter(|file| {
+                    file.category == subfolder.as_str() || file.category.starts_with(&prefix)
+                })
+                .count() as i32,
+            file: ModFile::default(),
+        });
+
+        if expanded {
+            add_tree_rows(rows, subfolder, depth + 1, folders, mod_files, collapsed);
+        }
+    }
+
+    for file in mod_files.iter().filter(|file| file.category == folder) {
+        rows.push(ModTreeRow {
+            is_folder: false,
+            folder: SharedString::from(folder),
+            label: file.name.clone(),
+            depth,
+            expanded: false,
+            file_count: 0,
+            file: file.clone(),
+        });
+    }
+}
+
+/// The folder a category is in, e.g. "maps" for "maps/megawads", empty at the top
+fn parent_folder(folder: &str) -> &str {
+    folder
+        .rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or("")
+}
+
+/// Rebuilds the list of packages from packages.toml
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+///
+/// A package is marked unavailable when one of its files is not in the mod list
+fn refresh_packages(app: &MenuApp) {
+    let mod_files = app.get_mod_files();
+    let packages: Vec<PackageFile> = package::load_packages()
+        .into_iter()
+        .map(|package| PackageFile {
+            available: package
+                .files
+                .iter()
+                .all(|key| mod_files.iter().any(|file| mod_key(&file) == key.as_str())),
+            name: SharedString::from(package.name),
+            source_archive: SharedString::from(package.source_archive),
+            files: ModelRc::new(VecModel::from(
+                package
+                    .files
+                    .into_iter()
+                    .map(SharedString::from)
+                    .collect::<Vec<_>>(),
+            )),
+        })
+        .collect();
+    app.set_packages(ModelRc::new(VecModel::from(packages)));
+}
+
+/// Replaces the load order with the files of a package, in the package's order
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - name of the package
+///
+/// #Returns
+/// - true if the load order was replaced, false if the package or one of its files is missing
+fn load_package(app: &MenuApp, name: &str) -> bool {
+    let Some(package) = package::load_packages()
+        .into_iter()
+        .find(|package| package.name == name)
+    else {
+        eprintln!("Unknown package {}", name);
+        return false;
+    };
+
+    let mod_files = app.get_mod_files();
+    let mut files = Vec::new();
+    for key in &package.files {
+        match mod_files.iter().find(|file| mod_key(file) == key.as_str()) {
+            Some(file) => files.push(file),
+            None => {
+                eprintln!("{} from package {} is missing", key, package.name);
+                return false;
+            }
+        }
+    }
+
+    if let Some(first) = files.first() {
+        pick_iwad_for(app, first);
+    }
+    set_load_order(app, files);
+    true
+}
+
+/// Launches the selected port and IWAD with the load order, start map and skill from the Play view
+///
+/// #Arguments
+/// - the MenuApp to read the selection from
+///
+/// #Returns
+/// - nothing, errors are reported to stderr
+///
+/// On success the IWAD and port are remembered for every file in the load order and
+/// the launch is counted in the library
+fn play_load_order(app: &MenuApp) {
+    let launcher = selected_port_path(app);
+    let wad = selected_iwad_path(app);
+    let load_order: Vec<ModFile> = app.get_loadOrder().iter().collect();
+    let mod_paths: Vec<String> = load_order
+        .iter()
+        .map(|file| file.path.to_string())
+        .collect();
+    let start_map = app.get_startMap();
+    let skill = app.get_skill();
+    // the saves and config profile belong to the first file, so adding patches or music
+    // keeps the same ones
+    let save_dir = load_order
+        .first()
+        .map(|file| saves::mod_save_dir(&mod_key(file)));
+    let config_file = load_order.first().and_then(|file| {
+        let port = source_port::SourcePort::from_executable(launcher.as_str())?;
+        profiles::profile_for(saves::Owner::Mod, &mod_key(file), port)
+    });
+
+    // an empty map and skill 0 mean "let GZDoom decide"
+    let start_map = (!start_map.is_empty()).then_some(start_map.as_str());
+    let skill = (1..=5).contains(&skill).then_some(skill as u8);
+
+    match commands::launch_game_with_mods(
+        &launcher,
+        &wad,
+        &mod_paths,
+        start_map,
+        skill,
+        save_dir.as_deref(),
+        config_file.as_deref(),
+    ) {
+        Ok(_) => {
+            eprintln!("Game launched successfully.");
+
+            if let Err(e) =
+                library::Library::open().and_then(|mut library| library.record_play(&mod_paths))
+            {
+                eprintln!("Failed to count the launch in the library: {}", e);
+            }
+
+            // remember the IWAD and port so they are picked automatically next time
+            config_functions::update_config(|config| {
+                for file in &load_order {
+                    let mod_settings = config.mods.entry(mod_key(file)).or_default();
+                    mod_settings.iwad = Some(wad.to_string());
+                    mod_settings.port = Some(launcher.to_string());
+                }
+            });
+            // the play count, last played date and IWAD filter changed
+            reload_library(app);
+        }
+        Err(e) => eprintln!("Failed to launch game: {}", e),
+    }
+}
+
+/// Rebuilds the list of presets shown in the Presets view from the config
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+fn refresh_presets(app: &MenuApp) {
+    let config = config_functions::load_config();
+    let file_name = |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string())
+    };
+
+    let presets: Vec<PresetFile> = config
+        .presets
+        .iter()
+        .map(|preset| {
+            let mut summary = vec![
+                preset
+                    .iwad
+                    .as_deref()
+                    .map(file_name)
+                    .unwrap_or_else(|| "default IWAD".to_string()),
+                preset
+                    .port
+                    .as_deref()
+                    .map(|path| {
+                        source_port::SourcePort::from_executable(path)
+                            .map(|port| port.name().to_string())
+                            .unwrap_or_else(|| file_name(path))
+                    })
+                    .unwrap_or_else(|| "default port".to_string()),
+                format!("{} mod(s)", preset.mods.len()),
+            ];
+            summary.extend(preset.warp.clone());
+            summary.extend(preset.skill.map(|skill| format!("skill {}", skill)));
+
+            PresetFile {
+                name: SharedString::from(preset.name.as_str()),
+                summary: SharedString::from(summary.join(" - ")),
+                args: SharedString::from(preset.args.join(" ")),
+                available: preset.mods.iter().all(|path| Path::new(path).is_file()),
+            }
+        })
+        .collect();
+    app.set_presets(ModelRc::new(VecModel::from(presets)));
+}
+
+/// Saves the selection of the Play view as a preset, replacing a preset with the same name
+///
+/// #Arguments
+/// - the MenuApp to read the selection from
+/// - name of the preset
+/// - extra arguments for the port, separated by spaces
+///
+/// #Returns
+/// - nothing
+fn save_preset(app: &MenuApp, name: &str, args: &str) {
+    let iwad = selected_iwad_path(app);
+    let port = selected_port_path(app);
+    let start_map = app.get_startMap();
+    let skill = app.get_skill();
+
+    let preset = config_functions::Preset {
+        name: name.trim().to_string(),
+        iwad: (!iwad.is_empty()).then(|| iwad.to_string()),
+        port: (!port.is_empty()).then(|| port.to_string()),
+        mods: app
+            .get_loadOrder()
+            .iter()
+            .map(|file| file.path.to_string())
+            .collect(),
+        warp: (!start_map.is_empty()).then(|| start_map.to_string()),
+        skill: (1..=5).contains(&skill).then_some(skill as u8),
+        args: args.split_whitespace().map(String::from).collect(),
+    };
+
+    config_functions::update_config(|config| {
+        match config
+            .presets
+            .iter_mut()
+            .find(|existing| existing.name.eq_ignore_ascii_case(&preset.name))
+        {
+            Some(existing) => *existing = preset,
+            None => config.presets.push(preset),
+        }
+    });
+    refresh_presets(app);
+}
+
+/// Puts the IWAD, port, mods, start map and skill of a preset into the Play view
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - name of the preset
+///
+/// #Returns
+/// - true if the Play view was updated, false if the preset or one of its files is missing
+///
+/// An IWAD or port that is not registered any more leaves the selection alone
+fn load_preset(app: &MenuApp, name: &str) -> bool {
+    let config = config_functions::load_config();
+    let Some(preset) = config.preset(name) else {
+        eprintln!("Unknown preset {}", name);
+        return false;
+    };
+
+    let mod_files = app.get_mod_files();
+    let mut files = Vec::new();
+    for path in &preset.mods {
+        match mod_files.iter().find(|file| file.path == path.as_str()) {
+            Some(file) => files.push(file),
+            None => {
+                eprintln!("{} from preset {} is missing", path, preset.name);
+                return false;
+            }
+        }
+    }
+
+    if let Some(index) = preset.iwad.as_ref().and_then(|path| {
+        app.get_iwads()
+            .iter()
+            .position(|iwad| iwad.path == path.as_str())
+    }) {
+        app.set_selectedIwad(index as i32);
+    }
+    if let Some(index) = preset.port.as_ref().and_then(|path| {
+        app.get_ports()
+            .iter()
+            .position(|port| port.path == path.as_str())
+    }) {
+        app.set_selectedPort(index as i32);
+    }
+
+    // the start map is set after the load order, which clears maps it doesn't have
+    set_load_order(app, files);
+    app.set_startMap(SharedString::from(preset.warp.clone().unwrap_or_default()));
+    app.set_skill(preset.skill.map(i32::from).unwrap_or_default());
+    true
+}
+
+/// Launches a preset without changing the Play view
+///
+/// #Arguments
+/// - name of the preset
+///
+/// #Returns
+/// - nothing, errors are reported to stderr
+///
+/// A preset without an IWAD or port uses the defaults from Settings
+fn launch_preset(name: &str) {
+    let config = config_functions::load_config();
+    let Some(preset) = config.preset(name) else {
+        eprintln!("Unknown preset {}", name);
+        return;
+    };
+
+    let wad = preset.iwad.as_ref().unwrap_or(&config.wad_path);
+    let launcher = preset.port.as_ref().unwrap_or(&config.source_port);
+
+    let save_dir = saves::preset_save_dir(&preset.name);
+    let config_file = source_port::SourcePort::from_executable(launcher)
+        .and_then(|port| profiles::profile_for(saves::Owner::Preset, &preset.name, port));
+    match commands::launch_preset(
+        launcher,
+        wad,
+        preset,
+        Some(&save_dir),
+        config_file.as_deref(),
+    ) {
+        Ok(_) => {
+            eprintln!("Game launched successfully.");
+            if let Err(e) =
+                library::Library::open().and_then(|mut library| library.record_play(&preset.mods))
+            {
+                eprintln!("Failed to count the launch in the library: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to launch game: {}", e),
+    }
+}
+
+/// Rebuilds the save browser from the save folders of every mod and preset
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+fn refresh_saves(app: &MenuApp) {
+    let mut rows = Vec::new();
+    for folder in saves::list_saves() {
+        rows.push(SaveRow {
+            is_folder: true,
+            label: SharedString::from(format!("{}: {}", folder.owner.as_str(), folder.name)),
+            details: SharedString::from(format!("{} save(s)", folder.saves.len())),
+        });
+        for save in folder.saves {
+            rows.push(SaveRow {
+                is_folder: false,
+                details: SharedString::from(format!(
+                    "{} - {} KB",
+                    save.modified_label(),
+                    save.size.div_ceil(1024)
+                )),
+                label: SharedString::from(save.name),
+            });
+        }
+    }
+    app.set_saves(ModelRc::new(VecModel::from(rows)));
+}
+
+/// Looks up the source port chosen in the Profiles view
+///
+/// #Arguments
+/// - the MenuApp to read from
+///
+/// #Returns
+/// - the SourcePort, or None if no supported port is registered
+fn selected_profile_port(app: &MenuApp) -> Option<source_port::SourcePort> {
+    let index = usize::try_from(app.get_profilePort()).ok()?;
+    let port = app.get_ports().row_data(index)?;
+    source_port::SourcePort::from_executable(port.path.as_str())
+}
+
+/// Reads a "mod: <path in the mods folder>" or "preset: <name>" label from the Profiles view
+fn parse_profile_owner(label: &str) -> Option<(saves::Owner, &str)> {
+    let (owner, name) = label.split_once(": ")?;
+    Some((saves::Owner::from_label(owner)?, name))
+}
+
+/// Rebuilds the profiles of the port chosen in the Profiles view and the mods and
+/// presets a profile can be made for
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+///
+/// The open profile is closed if it isn't listed for the port any more
+fn refresh_profiles(app: &MenuApp) {
+    let rows: Vec<ProfileRow> = selected_profile_port(app)
+        .map(profiles::list_profiles)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|profile| ProfileRow {
+            label: SharedString::from(match profile.owner {
+                Some(owner) => format!("{}: {}", owner.as_str(), profile.name),
+                None => "base".to_string(),
+            }),
+            path: SharedString::from(profile.path.to_string_lossy().as_ref()),
+        })
+        .collect();
+
+    let mut owners: Vec<SharedString> = app
+        .get_mod_files()
+        .iter()
+        .map(|file| SharedString::from(format!("mod: {}", mod_key(&file))))
+        .collect();
+    owners.extend(
+        config_functions::load_config()
+            .presets
+            .into_iter()
+            .map(|preset| SharedString::from(format!("preset: {}", preset.name))),
+    );
+
+    if !rows.iter().any(|row| row.path == app.get_profilePath()) {
+        app.set_profilePath(SharedString::new());
+        app.set_profileText(SharedString::new());
+        app.set_profileDiff(SharedString::new());
+    }
+    app.set_profiles(ModelRc::new(VecModel::from(rows)));
+    app.set_profileOwners(ModelRc::new(VecModel::from(owners)));
+}
+
+/// Opens a profile in the editor of the Profiles view
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - path of the profile, a base profile that doesn't exist yet opens empty
+///
+/// #Returns
+/// - nothing
+fn open_profile(app: &MenuApp, path: &Path) {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    app.set_profilePath(SharedString::from(path.to_string_lossy().as_ref()));
+    app.set_profileText(SharedString::from(text));
+    app.set_profileDiff(SharedString::new());
+}
+
+/// Copies a config into the profile of a mod or preset and opens the copy
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - the config to copy, the base profile of the port for a new profile
+/// - "mod: <path in the mods folder>" or "preset: <name>" label of the owner
+///
+/// #Returns
+/// - nothing, errors are reported to stderr
+fn copy_profile_to(app: &MenuApp, from: &Path, owner: &str) {
+    let (Some(port), Some((owner, name))) =
+        (selected_profile_port(app), parse_profile_owner(owner))
+    else {
+        return;
+    };
+
+    let to = profiles::profile_path(owner, name, port);
+    match profiles::copy_profile(from, &to) {
+        Ok(()) => {
+            refresh_profiles(app);
+            open_profile(app, &to);
+        }
+        Err(e) => eprintln!("Failed to write {}: {}", to.display(), e),
+    }
+}
+
+/// An archive, mod file or folder waiting to be imported
+struct ImportJob {
+    /// Row of the item in the import list
+    row: usize,
+    path: PathBuf,
+    mods_directory: String,
+}
+
+/// Adds an item to the import list and hands it to the import thread
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - sender of the import thread started by `spawn_import_thread`
+/// - path to the archive, mod file or folder
+///
+/// #Returns
+/// - nothing
+fn queue_import(app: &MenuApp, import_jobs: &mpsc::Sender<ImportJob>, path: PathBuf) {
+    let mut items: Vec<ImportItem> = app.get_imports().iter().collect();
+    let row = items.len();
+    items.push(ImportItem {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+            .into(),
+        status: SharedString::from("Queued"),
+        details: SharedString::new(),
+    });
+    app.set_imports(ModelRc::new(VecModel::from(items)));
+
+    let job = ImportJob {
+        row,
+        path,
+        mods_directory: app.get_modDirectory().to_string(),
+    };
+    if import_jobs.send(job).is_err() {
+        eprintln!("The import thread has stopped");
+    }
+}
+
+/// Starts the thread that imports queued items one at a time, so two imports never
+/// write the same file at once
+///
+/// #Arguments
+/// - the MenuApp to report progress to
+/// - sender of the refresh thread started by `spawn_refresh_thread`
+///
+/// #Returns
+/// - Sender to queue imports with
+fn spawn_import_thread(app: &MenuApp, refresh: mpsc::Sender<()>) -> mpsc::Sender<ImportJob> {
+    let (sender, receiver) = mpsc::channel::<ImportJob>();
+    let app_weak = app.as_weak();
+
+    std::thread::spawn(move || {
+        for job in receiver {
+            set_import_status(&app_weak, job.row, "Importing...", String::new());
+
+            let settings = config_functions::load_config().import;
+            let report = import::import_path(&job.path, &job.mods_directory, &settings);
+            let status = if report.error.is_some() {
+                "Failed"
+            } else {
+                "Done"
+            };
+            set_import_status(&app_weak, job.row, status, report.summary());
+
+            // packages can change without any file in the mods directory changing
+            let _ = refresh.send(());
+        }
+    });
+
+    sender
+}
+
+/// Updates a row of the import list from the import thread
+fn set_import_status(app_weak: &Weak<MenuApp>, row: usize, status: &'static str, details: String) {
+    // the UI can only be updated from the event loop
+    let _ = app_weak.upgrade_in_event_loop(move |app| {
+        let imports = app.get_imports();
+        if let Some(mut item) = imports.row_data(row) {
+            item.status = SharedString::from(status);
+            item.details = SharedString::from(details);
+            imports.set_row_data(row, item);
+        }
+    });
+}
+
+/// Starts the thread that rescans the mods directory from the config once it stops changing
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - Sender to ask for a rescan with
+fn spawn_refresh_thread(app: &MenuApp) -> mpsc::Sender<()> {
+    let (sender, receiver) = mpsc::channel::<()>();
+    let app_weak = app.as_weak();
+
+    std::thread::spawn(move || {
+        while receiver.recv().is_ok() {
+            // wait until no more requests come in
+            loop {
+                match receiver.recv_timeout(REFRESH_DELAY) {
+                    Ok(()) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            // the scan reads and hashes new and changed files, so it runs on this thread and
+            // only the finished list is handed to the event loop
+            let mods_directory = config_functions::load_config().mods_directory;
+            let mod_map = utility::get_mod_files_in_folder(&mods_directory);
+            let _ = app_weak.upgrade_in_event_loop(move |app| show_mod_files(&app, mod_map));
+        }
+    });
+
+    sender
+}
+
+/// Watches the mods directory and its subfolders for files that are added, removed, renamed
+/// or changed
+///
+/// #Arguments
+/// - sender of the refresh thread started by `spawn_refresh_thread`
+/// - path to the mods directory as a string reference
+///
+/// #Returns
+/// - the watcher, which stops watching when it is dropped
+/// - None if no folder is set or it can't be watched
+fn watch_mods_directory(
+    refresh: mpsc::Sender<()>,
+    folder_path: &str,
+) -> Option<RecommendedWatcher> {
+    if folder_path == "empty" || folder_path.is_empty() {
+        return None;
+    }
+
+    let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        match result {
+            // scanning opens and reads the files, that must not trigger another scan
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+            Ok(_) => {
+                let _ = refresh.send(());
+            }
+            Err(e) => eprintln!("Error watching the mods directory: {}", e),
+        }
+    });
+
+    match watcher.and_then(|mut watcher| {
+        watcher.watch(Path::new(folder_path), RecursiveMode::Recursive)?;
+        Ok(watcher)
+    }) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            eprintln!("Failed to watch {}: {}", folder_path, e);
+            None
+        }
+    }
+}
+
+/// Identifies the IWAD at a path and describes it for the Settings view
+///
+/// #Arguments
+/// - path to the wad file as a string reference
+///
+/// #Returns
+/// - description such as "Doom II v1.9 (verified)", or empty if no wad has been chosen
+fn describe_wad(wad_path: &str) -> SharedString {
+    if wad_path == "empty" || wad_path.is_empty() {
+        return SharedString::new();
+    }
+    SharedString::from(iwad::identify_iwad(wad_path).describe())
+}
+
+/// Rebuilds the list of IWADs that can be chosen when launching
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+///
+/// The default IWAD from Settings comes first, followed by the additional IWADs in the config
+fn refresh_iwads(app: &MenuApp) {
+    let config = config_functions::load_config();
+
+    let mut paths: Vec<String> = Vec::new();
+    let default_wad = app.get_wadFile().to_string();
+    if default_wad != "empty" && !default_wad.is_empty() {
+        paths.push(default_wad);
+    }
+    for path in config.iwads {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    let iwads: Vec<IwadFile> = paths
+        .into_iter()
+        .map(|path| {
+            let file_name = std::path::Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            IwadFile {
+                name: SharedString::from(format!("{} - {}", file_name, describe_wad(&path))),
+                path: SharedString::from(path),
+            }
+        })
+        .collect();
+
+    let names: Vec<SharedString> = iwads.iter().map(|iwad| iwad.name.clone()).collect();
+
+    if app.get_selectedIwad() >= iwads.len() as i32 {
+        app.set_selectedIwad(0);
+    }
+
+    app.set_iwadNames(ModelRc::new(VecModel::from(names)));
+    app.set_iwads(ModelRc::new(VecModel::from(iwads)));
+}
+
+/// Rebuilds the list of source ports that can be chosen when launching
+///
+/// #Arguments
+/// - the MenuApp to update
+///
+/// #Returns
+/// - nothing
+///
+/// The default port from Settings comes first, followed by the additional ports in the config
+fn refresh_ports(app: &MenuApp) {
+    let config = config_functions::load_config();
+
+    let mut paths: Vec<String> = Vec::new();
+    let default_port = app.get_launcherPath().to_string();
+    if source_port::SourcePort::from_executable(&default_port).is_some() {
+        paths.push(default_port);
+    }
+    for path in config.ports {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    let ports: Vec<PortFile> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let port = source_port::SourcePort::from_executable(&path)?;
+            Some(PortFile {
+                name: SharedString::from(format!("{} - {}", port.name(), path)),
+                path: SharedString::from(path),
+            })
+        })
+        .collect();
+
+    let names: Vec<SharedString> = ports.iter().map(|port| port.name.clone()).collect();
+
+    if app.get_selectedPort() >= ports.len() as i32 {
+        app.set_selectedPort(0);
+    }
+
+    app.set_portNames(ModelRc::new(VecModel::from(names)));
+    app.set_ports(ModelRc::new(VecModel::from(ports)));
+}
+
+/// Chooses the IWAD and source port for a mod when it is the first file added to the load order
+///
+/// #Arguments
+/// - the MenuApp to update
+/// - the mod that was added
+///
+/// #Returns
+/// - nothing
+///
+/// The IWAD the mod was last played with wins. Otherwise the IWAD named in the mod's
+/// GAMEINFO is matched by file name. If neither is found the selection is left alone.
+/// The source port is only changed if the mod has been played with one before.
+fn pick_iwad_for(app: &MenuApp, file: &ModFile) {
+    let config = config_functions::load_config();
+    let mod_settings = config.mods.get(&mod_key(file)).cloned().unwrap_or_default();
+    let iwads: Vec<IwadFile> = app.get_iwads().iter().collect();
+
+    let remembered = mod_settings
+        .iwad
+        .and_then(|path| iwads.iter().position(|iwad| iwad.path == path.as_str()));
+
+    let required = (!file.required_iwad.is_empty())
+        .then(|| {
+            iwads.iter().position(|iwad| {
+                std::path::Path::new(iwad.path.as_str())
+                    .file_name()
+                    .is_some_and(|name| {
+                        name.to_string_lossy().to_lowercase() == file.required_iwad.as_str()
+                    })
+            })
+        })
+        .flatten();
+
+    if let Some(index) = remembered.or(required) {
+        app.set_selectedIwad(index as i32);
+    }
+
+    // the port the mod was last played with
+    let ports: Vec<PortFile> = app.get_ports().iter().collect();
+    if let Some(index) = mod_settings
+        .port
+        .and_then(|path| ports.iter().position(|port| port.path == path.as_str()))
+    {
+        app.set_selectedPort(index as i32);
+    }
+}
+
+/// Looks up the path of the IWAD chosen in the Play view
+///
+/// #Arguments
+/// - the MenuApp to read from
+///
+/// #Returns
+/// - path to the IWAD, or an empty string if none are registered
+fn selected_iwad_path(app: &MenuApp) -> SharedString {
+    let index = app.get_selectedIwad();
+    if index < 0 {
+        return SharedString::new();
+    }
+    app.get_iwads()
+        .row_data(index as usize)
+        .map(|iwad| iwad.path)
+        .unwrap_or_default()
+}
+
+/// Looks up the path of the source port chosen in the Play view
+///
+/// #Arguments
+/// - the MenuApp to read from
+///
+/// #Returns
+/// - path to the port executable, or an empty string if none are registered
+fn selected_port_path(app: &MenuApp) -> SharedString {
+    let index = app.get_selectedPort();
+    if index < 0 {
+        return SharedString::new();
+    }
+    app.get_ports()
+        .row_data(index as usize)
+        .map(|port| port.path)
+        .unwrap_or_default()
+}
 
 /// Main function for imp-orter
-/// Initializes the Slint UI, loads configuration settings, and sets up event callbacks.
+/// Runs a headless subcommand if one was given on the command line.
+/// Otherwise initializes the Slint UI, loads configuration settings, and sets up event callbacks.
 fn main() {
+    let cli = cli::Cli::parse();
+
+    // resolve where the config, data and cache live before anything reads them
+    paths::init(cli.config, cli.portable);
+
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command, cli.json));
+    }
 
     let description = include_str!("../documents/description.txt");
 
-    println!("DEBUG - Config File Path: {:?}", config_functions::get_config_path());
+    eprintln!("DEBUG - Config File Path: {:?}", config_functions::get_config_path());
 
     // import the Slint ui menu
     let app = MenuApp::new().unwrap();
     app.set_description(description.into());
 
-    let config = config_functions::read_config("documents/config.txt");
+    let config = config_functions::load_config();
 
-    let gzdoom_path = config.get("gzDoom_Path").cloned().unwrap_or_default();
-    let wad_path = config.get("wad_Path").cloned().unwrap_or_default();
-    let mods_directory = config.get("mods_Directory").cloned().unwrap_or_default();
+    let gzdoom_path = config.source_port.clone();
+    let wad_path = config.wad_path.clone();
+    let mods_directory = config.mods_directory.clone();
 
-    app.set_launcherPath(SharedString::from(
-        config.get("gzDoom_Path").cloned().unwrap_or_default(),
-    ));
-    app.set_wadFile(SharedString::from(
-        config.get("wad_Path").cloned().unwrap_or_default(),
-    ));
-    app.set_modDirectory(SharedString::from(
-        config.get("mods_Directory").cloned().unwrap_or_default(),
-    ));
+    app.set_launcherPath(SharedString::from(config.source_port.as_str()));
+    app.set_wadFile(SharedString::from(config.wad_path.as_str()));
+    app.set_modDirectory(SharedString::from(config.mods_directory.as_str()));
+    app.set_wadInfo(describe_wad(&wad_path));
+    refresh_iwads(&app);
+    refresh_ports(&app);
 
-    println!("DEBUG -- gzDoom_Path: {}", gzdoom_path);
-    println!("DEBUG -- wad_Path: {}", wad_path);
-    println!("DEBUG -- mods_Directory: {}", mods_directory);
+    eprintln!("DEBUG -- source_port: {}", gzdoom_path);
+    eprintln!("DEBUG -- wad_path: {}", wad_path);
+    eprintln!("DEBUG -- mods_directory: {}", mods_directory);
 
     // retrieve wad file paths in the mods folder
-    let mod_map = utility::get_wad_files_in_folder(&mods_directory);
+    let mod_map = utility::get_mod_files_in_folder(&mods_directory);
 
-    println!("DEBUG - Mod Mappings: {:?}", mod_map);
+    eprintln!("DEBUG - Mod Mappings: {:?}", mod_map);
 
     // Converts the Hashmap into a Vector of ModFiles (Modfile is a struct defined in Slint)
-    let mod_files: Vec<ModFile> = mod_map
-        .into_iter()
-        .map(|(name, path)| ModFile {
-            name: SharedString::from(name),
-            path: SharedString::from(path),
-        })
-        .collect();
+    let mod_files = sorted_by_name(to_mod_files(
+        mod_map
+            .into_values()
+            .map(|entry| (entry, library::UserData::default())),
+    ));
 
-    println!("DEBUG - Vectorized Mod Mappings: {:?}", mod_files);
+    eprintln!("DEBUG - Vectorized Mod Mappings: {:?}", mod_files);
 
     let model_rc = ModelRc::new(VecModel::from(mod_files));
-    println!("DEBUG - ModelRc content: {:?}", model_rc.row_count());
+    eprintln!("DEBUG - ModelRc content: {:?}", model_rc.row_count());
     app.set_mod_files(model_rc);
+    reload_library(&app);
+    refresh_packages(&app);
+    refresh_presets(&app);
 
-    //Callback for getting the GZ Doom Launcher
-    app.on_getGzDoomLauncher({
+    //Callback for getting the default source port
+    app.on_getSourcePort({
         let app_handle = app.clone_strong();
         move || {
-            let exe_path = SharedString::from(utility::select_executable());
+            let Some(exe_path) = utility::select_executable() else {
+                return; // canceled or not a supported port
+            };
+            let exe_path = SharedString::from(exe_path);
 
             app_handle.set_launcherPath(exe_path.clone()); // Pass a cloned value to UI
 
-            let config_path = std::env::current_dir()
-                .unwrap()
-                .join("documents/config.txt");
-            config_functions::update_gzdoom_path(config_path.to_str().unwrap(), exe_path.as_str());
-            // Use as &str
+            config_functions::update_config(|config| config.source_port = exe_path.to_string());
+
+            refresh_ports(&app_handle);
+        }
+    });
+
+    //Callbacks for registering additional source ports
+    app.on_addPort({
+        let app_handle = app.clone_strong();
+        move || {
+            let Some(exe_path) = utility::select_executable() else {
+                return; // canceled or not a supported port
+            };
+
+            config_functions::update_config(|config| {
+                if !config.ports.contains(&exe_path) {
+                    config.ports.push(exe_path);
+                }
+            });
+
+            refresh_ports(&app_handle);
+        }
+    });
+
+    app.on_removePort({
+        let app_handle = app.clone_strong();
+        move |index| {
+            let Some(port) = app_handle.get_ports().row_data(index as usize) else {
+                return;
+            };
+
+            config_functions::update_config(|config| {
+                config.ports.retain(|path| path != port.path.as_str())
+            });
+
+            refresh_ports(&app_handle);
         }
     });
 
@@ -77,48 +1293,132 @@ fn main() {
     app.on_getWadPath({
         let app_handle = app.clone_strong();
         move || {
-            let file_path = SharedString::from(utility::get_wad_path());
+            let Some(file_path) = utility::get_wad_path() else {
+                return; // canceled
+            };
+            let file_path = SharedString::from(file_path);
 
             app_handle.set_wadFile(file_path.clone());
+            app_handle.set_wadInfo(describe_wad(&file_path));
 
-            let config_path = std::env::current_dir()
-                .unwrap()
-                .join("documents/config.txt");
-            config_functions::update_wad_path(config_path.to_str().unwrap(), file_path.as_str());
-            // Use as &str
+            config_functions::update_config(|config| config.wad_path = file_path.to_string());
+
+            refresh_iwads(&app_handle);
         }
     });
 
+    //Callbacks for registering additional IWADs
+    app.on_addIwad({
+        let app_handle = app.clone_strong();
+        move || {
+            let Some(file_path) = utility::get_wad_path() else {
+                return; // canceled
+            };
+
+            config_functions::update_config(|config| {
+                if !config.iwads.contains(&file_path) {
+                    config.iwads.push(file_path);
+                }
+            });
+
+            refresh_iwads(&app_handle);
+        }
+    });
+
+    app.on_removeIwad({
+        let app_handle = app.clone_strong();
+        move |index| {
+            let Some(iwad) = app_handle.get_iwads().row_data(index as usize) else {
+                return;
+            };
+
+            config_functions::update_config(|config| {
+                config.iwads.retain(|path| path != iwad.path.as_str())
+            });
+
+            refresh_iwads(&app_handle);
+        }
+    });
+
+    // Rescan the mods directory whenever files in it change, the watcher is replaced
+    // when another folder is chosen
+    let refresh = spawn_refresh_thread(&app);
+    let mods_watcher = Rc::new(RefCell::new(watch_mods_directory(
+        refresh.clone(),
+        &mods_directory,
+    )));
+
     //Callback for getting the modsDirectory
-    // - needs a clone of the mods directory to be move into the function and update the directory listing
-    let mod_d = mods_directory.clone();
     app.on_getModDirectory({
         let app_handle = app.clone_strong();
+        let refresh = refresh.clone();
         move || {
             let folder_path = SharedString::from(utility::get_folder_path());
 
             app_handle.set_modDirectory(folder_path.clone());
 
-            let config_path = std::env::current_dir()
-                .unwrap()
-                .join("documents/config.txt");
-            config_functions::update_wad_directory(
-                config_path.to_str().unwrap(),
-                folder_path.as_str(),
-            );
-
-            let mod_map = utility::get_wad_files_in_folder(&mod_d);
-            let mod_files: Vec<ModFile> = mod_map
-                .into_iter()
-                .map(|(name, path)| ModFile {
-                    name: SharedString::from(name),
-                    path: SharedString::from(path),
-                })
-                .collect();
+            config_functions::update_config(|config| {
+                config.mods_directory = folder_path.to_string()
+            });
 
+            *mods_watcher.borrow_mut() = watch_mods_directory(refresh.clone(), &folder_path);
+            let _ = refresh.send(());
+        }
+    });
 
-            let model_rc = ModelRc::new(VecModel::from(mod_files));
-            app_handle.set_mod_files(model_rc);
+    // Callback to collapse or expand a folder of the mod tree
+    app.on_toggleFolder({
+        let app_handle = app.clone_strong();
+        move |folder| {
+            let tree = app_handle.get_modTree();
+            for (index, mut row) in tree.iter().enumerate() {
+                if row.is_folder && row.folder == folder {
+                    row.expanded = !row.expanded;
+                    tree.set_row_data(index, row);
+                }
+            }
+            refresh_mod_tree(&app_handle);
+        }
+    });
+
+    // Callback to show the mods matching the search text and filters
+    app.on_filterChanged({
+        let app_handle = app.clone_strong();
+        move || refresh_mod_tree(&app_handle)
+    });
+
+    // Callback to store the tags, rating, notes, favorite and status edited in the details pane
+    app.on_saveUserData({
+        let app_handle = app.clone_strong();
+        move |file| {
+            let user = library::UserData {
+                tags: file.tags.split(',').map(String::from).collect(),
+                rating: u8::try_from(file.rating).ok().filter(|rating| *rating > 0),
+                notes: file.notes.to_string(),
+                favorite: file.favorite,
+                status: library::PlayStatus::from_label(&file.status),
+            };
+            match library::Library::open()
+                .and_then(|mut library| library.set_user_data(&file.path, &user))
+            {
+                Ok(()) => reload_library(&app_handle),
+                Err(e) => eprintln!("Failed to save user data for {}: {}", file.path, e),
+            }
+        }
+    });
+
+    // Callback to write the user data of every library item to a JSON file
+    app.on_exportUserData(|| {
+        let Some(path) = utility::get_export_path() else {
+            return;
+        };
+        let result = library::Library::open()
+            .and_then(|library| library.export_user_data())
+            .and_then(|items| serde_json::to_string_pretty(&items).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => eprintln!("Exported user data to {}", path.display()),
+            Err(e) => eprintln!("Failed to export user data: {}", e),
         }
     });
 
@@ -126,46 +1426,264 @@ fn main() {
     app.on_playVanilla({
         let app_handle = app.clone_strong();
         move || {
-            println!("Launching Vanilla");
-            let launcher = app_handle.get_launcherPath();
-            let wad = app_handle.get_wadFile();
+            eprintln!("Launching Vanilla");
+            let launcher = selected_port_path(&app_handle);
+            let wad = selected_iwad_path(&app_handle);
 
-            match commands::launch_gzdoom(&launcher, &wad) {
-                Ok(_) => println!("GZDoom launched successfully."),
-                Err(e) => eprintln!("Failed to launch GZDoom: {}", e),
+            match commands::launch_game(&launcher, &wad) {
+                Ok(_) => eprintln!("Game launched successfully."),
+                Err(e) => eprintln!("Failed to launch game: {}", e),
             }
         }
     });
 
-    // Callback for starting GZDoom with a selected mod
+    // Callbacks for editing the load order of the mods to launch
+    app.on_addToLoadOrder({
+        let app_handle = app.clone_strong();
+        move |file| {
+            let mut files: Vec<ModFile> = app_handle.get_loadOrder().iter().collect();
+            if files.iter().any(|f| f.path == file.path) {
+                return; // already in the load order
+            }
+            if files.is_empty() {
+                pick_iwad_for(&app_handle, &file);
+            }
+            files.push(file);
+            set_load_order(&app_handle, files);
+        }
+    });
+
+    app.on_removeFromLoadOrder({
+        let app_handle = app.clone_strong();
+        move |index| {
+            let mut files: Vec<ModFile> = app_handle.get_loadOrder().iter().collect();
+            if index >= 0 && (index as usize) < files.len() {
+                files.remove(index as usize);
+                set_load_order(&app_handle, files);
+            }
+        }
+    });
+
+    app.on_moveInLoadOrder({
+        let app_handle = app.clone_strong();
+        move |index, delta| {
+            let mut files: Vec<ModFile> = app_handle.get_loadOrder().iter().collect();
+            let target = index + delta;
+            if index >= 0
+                && target >= 0
+                && (index as usize) < files.len()
+                && (target as usize) < files.len()
+            {
+                files.swap(index as usize, target as usize);
+                set_load_order(&app_handle, files);
+            }
+        }
+    });
+
+    // Callback for starting GZDoom with the mods in the load order
     app.on_playMod({
         let app_handle = app.clone_strong();
         move || {
-            println!("Launching Mod");
-            let launcher = app_handle.get_launcherPath();
-            let wad = app_handle.get_wadFile();
-            let mod_file = app_handle.get_selectedWad();
+            eprintln!("Launching Mod");
+            play_load_order(&app_handle);
+        }
+    });
+
+    // Callbacks for packages: load the files of one archive into the load order, or play them
+    app.on_loadPackage({
+        let app_handle = app.clone_strong();
+        move |name| {
+            load_package(&app_handle, &name);
+        }
+    });
 
-            match commands::launch_gzdoom_with_mod(&launcher, &wad, &mod_file) {
-                Ok(_) => println!("GZDoom launched successfully."),
-                Err(e) => eprintln!("Failed to launch GZDoom: {}", e),
+    app.on_playPackage({
+        let app_handle = app.clone_strong();
+        move |name| {
+            eprintln!("Launching Package {}", name);
+            if load_package(&app_handle, &name) {
+                play_load_order(&app_handle);
             }
         }
     });
 
-    // Callback for Importing new WADS from a zip folder
-    app.on_importWad(move || {
-        println!("Import WAD");
+    // Callbacks for presets: save the Play view under a name, launch, load into Play or delete one
+    app.on_savePreset({
+        let app_handle = app.clone_strong();
+        move |name, args| save_preset(&app_handle, &name, &args)
+    });
 
-        let value = mods_directory.clone();
-        std::thread::spawn(move || {
-            // Run in a separate thread to avoid blocking UI
-            if let Err(e) = commands::extract_and_move_wad(&value) {
-                eprintln!("Failed to extract WAD: {}", e);
-            } else {
-                println!("WAD extracted successfully.");
+    app.on_launchPreset(|name| {
+        eprintln!("Launching Preset {}", name);
+        launch_preset(&name);
+    });
+
+    app.on_loadPreset({
+        let app_handle = app.clone_strong();
+        move |name| {
+            if load_preset(&app_handle, &name) {
+                app_handle.set_current_view(SharedString::from("Play"));
             }
-        });
+        }
+    });
+
+    app.on_deletePreset({
+        let app_handle = app.clone_strong();
+        move |name| {
+            config_functions::update_config(|config| {
+                config
+                    .presets
+                    .retain(|preset| !preset.name.eq_ignore_ascii_case(&name))
+            });
+            refresh_presets(&app_handle);
+        }
+    });
+
+    // Callback to list the saves of every mod and preset
+    app.on_refreshSaves({
+        let app_handle = app.clone_strong();
+        move || refresh_saves(&app_handle)
+    });
+
+    // Callbacks for the Profiles view: port configs passed with -config for a mod or preset
+    app.on_refreshProfiles({
+        let app_handle = app.clone_strong();
+        move || refresh_profiles(&app_handle)
+    });
+
+    app.on_importBaseProfile({
+        let app_handle = app.clone_strong();
+        move || {
+            let (Some(port), Some(from)) = (
+                selected_profile_port(&app_handle),
+                utility::get_port_config_path(),
+            ) else {
+                return;
+            };
+            let base = profiles::base_profile(port);
+            match profiles::copy_profile(&from, &base) {
+                Ok(()) => open_profile(&app_handle, &base),
+                Err(e) => eprintln!("Failed to write {}: {}", base.display(), e),
+            }
+        }
+    });
+
+    app.on_openProfile({
+        let app_handle = app.clone_strong();
+        move |path| open_profile(&app_handle, Path::new(path.as_str()))
+    });
+
+    app.on_createProfile({
+        let app_handle = app.clone_strong();
+        move |owner| {
+            if let Some(port) = selected_profile_port(&app_handle) {
+                copy_profile_to(&app_handle, &profiles::base_profile(port), &owner);
+            }
+        }
+    });
+
+    app.on_copyProfile({
+        let app_handle = app.clone_strong();
+        move |path, owner| copy_profile_to(&app_handle, Path::new(path.as_str()), &owner)
+    });
+
+    app.on_saveProfile({
+        let app_handle = app.clone_strong();
+        move |path, text| {
+            let path = Path::new(path.as_str());
+            let result = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, text.as_str()));
+            match result {
+                Ok(()) => {
+                    eprintln!("Saved {}", path.display());
+                    app_handle.set_profileDiff(SharedString::new());
+                }
+                Err(e) => eprintln!("Failed to write {}: {}", path.display(), e),
+            }
+        }
+    });
+
+    app.on_diffProfile({
+        let app_handle = app.clone_strong();
+        move |path| {
+            let Some(port) = selected_profile_port(&app_handle) else {
+                return;
+            };
+            let diff = match profiles::diff_with_base(Path::new(path.as_str()), port) {
+                Ok(lines) if lines.is_empty() => "Same as the base profile".to_string(),
+                Ok(lines) => lines.join("\n"),
+                Err(e) => format!("Failed to read {}: {}", path, e),
+            };
+            app_handle.set_profileDiff(SharedString::from(diff));
+        }
+    });
+
+    app.on_resetProfile({
+        let app_handle = app.clone_strong();
+        move |path| {
+            let Some(port) = selected_profile_port(&app_handle) else {
+                return;
+            };
+            let path = Path::new(path.as_str());
+            match profiles::copy_profile(&profiles::base_profile(port), path) {
+                Ok(()) => open_profile(&app_handle, path),
+                Err(e) => eprintln!("Failed to reset {}: {}", path.display(), e),
+            }
+        }
+    });
+
+    app.on_deleteProfile({
+        let app_handle = app.clone_strong();
+        move |path| {
+            if let Err(e) = fs::remove_file(path.as_str()) {
+                eprintln!("Failed to delete {}: {}", path, e);
+            }
+            refresh_profiles(&app_handle);
+        }
+    });
+
+    // archives, mod files and folders are imported one at a time on a background thread
+    let import_jobs = spawn_import_thread(&app, refresh);
+
+    // Callback for Importing new WADS from an archive
+    app.on_importWad({
+        let app_weak = app.as_weak();
+        let import_jobs = import_jobs.clone();
+        move || {
+            eprintln!("Import WAD");
+
+            let app_weak = app_weak.clone();
+            let import_jobs = import_jobs.clone();
+            std::thread::spawn(move || {
+                // Run in a separate thread to avoid blocking UI
+                let Some(archive_path) = utility::get_archive_path() else {
+                    return;
+                };
+
+                let _ = app_weak.upgrade_in_event_loop(move |app| {
+                    queue_import(&app, &import_jobs, archive_path);
+                });
+            });
+        }
+    });
+
+    // Files and folders dropped on the window are imported like the Import button does
+    app.window().on_winit_window_event({
+        let app_handle = app.clone_strong();
+        move |_, event| {
+            match event {
+                WindowEvent::HoveredFile(_) => app_handle.set_dropHover(true),
+                WindowEvent::HoveredFileCancelled => app_handle.set_dropHover(false),
+                WindowEvent::DroppedFile(path) => {
+                    app_handle.set_dropHover(false);
+                    queue_import(&app_handle, &import_jobs, path.clone());
+                }
+                _ => {}
+            }
+            EventResult::Propagate
+        }
     });
 
     app.run().unwrap();
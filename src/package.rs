@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// The mod files that were imported together from one archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    /// Name of the archive without its extension, e.g. "mapset-v1.1" for mapset-v1.1.tar.gz
+    pub name: String,
+    /// File name of the archive the files came from, a package is replaced when the same
+    /// archive is imported again
+    pub source_archive: String,
+    /// Paths of the mod files relative to the mods directory, in the order they should be
+    /// loaded. Imported files are written to the top of the mods directory, so these are
+    /// their file names
+    pub files: Vec<String>,
+}
+
+/// Everything stored in packages.toml
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PackageList {
+    package: Vec<Package>,
+}
+
+/// Function to get the path of packages.toml
+///
+/// #Arguments
+///  - none
+///
+/// #Returns
+/// - PathBuf inside the data directory
+pub fn get_packages_path() -> PathBuf {
+    paths::get().data_dir.join("packages.toml")
+}
+
+/// Function to load every recorded package
+///
+/// #Arguments
+///  - none
+///
+/// #Returns
+/// - Vector of Packages sorted by name, empty if none were recorded or the file can't be read
+pub fn load_packages() -> Vec<Package> {
+    let path = get_packages_path();
+    if !path.exists() {
+        return Vec::new();
+    }
+
+    let mut packages = match read_packages(&path) {
+        Ok(packages) => packages,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            Vec::new()
+        }
+    };
+
+    packages.sort_by_key(|package| package.name.to_lowercase());
+    packages
+}
+
+/// Reads and parses a packages.toml file
+fn read_packages(path: &Path) -> Result<Vec<Package>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str::<PackageList>(&contents)
+        .map(|list| list.package)
+        .map_err(|e| e.to_string())
+}
+
+/// Function to record a package, replacing the package of the same archive
+///
+/// #Arguments
+/// - the Package to store
+///
+/// #Returns
+/// - an io error if packages.toml can't be read or written
+///
+/// A packages.toml that can't be parsed is left alone, so the packages in it are not lost.
+/// The file is written to a temporary file first and then renamed over the old one.
+pub fn save_package(package: &Package) -> std::io::Result<()> {
+    write_package(&get_packages_path(), package)
+}
+
+/// Adds a package to a packages.toml file, see `save_package`
+fn write_package(path: &Path, package: &Package) -> std::io::Result<()> {
+    let mut packages = if path.exists() {
+        read_packages(path).map_err(|e| {
+            std::io::Error::other(format!(
+                "{} could not be read ({}), fix or remove it to record packages",
+                path.display(),
+                e
+            ))
+        })?
+    } else {
+        Vec::new()
+    };
+    packages.retain(|existing| existing.source_archive != package.source_archive);
+    packages.push(package.clone());
+    packages.sort_by_key(|package| package.name.to_lowercase());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let contents = toml::to_string_pretty(&PackageList { package: packages })
+        .map_err(std::io::Error::other)?;
+    let temp_path = path.with_extension("toml.tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+/// Function to name a package after the archive it was imported from
+///
+/// #Arguments
+/// - file name of the archive
+///
+/// #Returns
+/// - the file name without its archive extension, e.g. "sunlust-v1.1" for sunlust-v1.1.zip
+///   and "mapset" for mapset.tar.gz. Other dots in the name are kept
+pub fn name_for_archive(source_archive: &str) -> String {
+    const SUFFIXES: [&str; 11] = [
+        ".tar.gz", ".tar.xz", ".tar.bz2", ".tgz", ".txz", ".tbz2", ".tbz", ".zip", ".7z", ".tar",
+        ".rar",
+    ];
+
+    SUFFIXES
+        .iter()
+        .find_map(|suffix| {
+            let stem_len = source_archive.len().checked_sub(suffix.len())?;
+            let extension = source_archive.get(stem_len..)?;
+            (stem_len > 0 && extension.eq_ignore_ascii_case(suffix))
+                .then(|| source_archive[..stem_len].to_string())
+        })
+        .unwrap_or_else(|| source_archive.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, source_archive: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            source_archive: source_archive.to_string(),
+            files: vec![format!("{}.wad", name)],
+        }
+    }
+
+    #[test]
+    fn replaces_the_package_of_the_same_archive() {
+        let dir = std::env::temp_dir().join(format!("imp-porter-package-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("packages.toml");
+        let _ = fs::remove_file(&path);
+
+        write_package(&path, &package("sunlust", "sunlust.zip")).unwrap();
+        write_package(&path, &package("sunlust", "sunlust.7z")).unwrap();
+        let mut updated = package("sunlust", "sunlust.zip");
+        updated.files.push("music.wad".to_string());
+        write_package(&path, &updated).unwrap();
+
+        let stored = read_packages(&path).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.contains(&updated));
+        assert!(!path.with_extension("toml.tmp").exists());
+    }
+
+    #[test]
+    fn never_overwrites_an_unreadable_packages_file() {
+        let dir =
+            std::env::temp_dir().join(format!("imp-porter-package-broken-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("packages.toml");
+        fs::write(&path, "[[package]\nname = ").unwrap();
+
+        assert!(write_package(&path, &package("sunlust", "sunlust.zip")).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[[package]\nname = ");
+    }
+
+    #[test]
+    fn names_packages_without_the_archive_extension() {
+        assert_eq!(name_for_archive("sunlust-v1.1.zip"), "sunlust-v1.1");
+        assert_eq!(name_for_archive("sunlust-v1.2.ZIP"), "sunlust-v1.2");
+        assert_eq!(name_for_archive("mapset.tar.gz"), "mapset");
+        assert_eq!(name_for_archive("mapset.v2.tgz"), "mapset.v2");
+        assert_eq!(name_for_archive("mapset.tar.bz2"), "mapset");
+        assert_eq!(name_for_archive("mapset.7z"), "mapset");
+        // a file that is only an extension, or has no known one, keeps its name
+        assert_eq!(name_for_archive(".zip"), ".zip");
+        assert_eq!(name_for_archive("mapset.dat"), "mapset.dat");
+    }
+}
@@ -0,0 +1,202 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Name of the folder created inside the config, data and cache directories
+const APP_DIR_NAME: &str = "imp-porter";
+
+/// Marker file that turns on portable mode when it sits next to the executable
+const PORTABLE_MARKER: &str = "imp-porter.portable";
+
+/// Where imp-orter keeps its files
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    /// The config.toml file
+    pub config_file: PathBuf,
+    /// Folder for the library database and other files imp-orter manages
+    pub data_dir: PathBuf,
+    /// Folder for files that can be rebuilt at any time
+    pub cache_dir: PathBuf,
+}
+
+static APP_PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+/// Function to resolve the config, data and cache locations from the command line
+/// and environment. Must be called once at startup before any config is read.
+///
+/// #Arguments
+/// - optional config file given with --config
+/// - true if --portable was given, to keep everything next to the executable
+///
+/// #Returns
+/// - nothing
+///
+/// Portable mode is also turned on when a file named imp-porter.portable is next to the executable
+pub fn init(config_override: Option<PathBuf>, mut portable: bool) {
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+    if let Some(dir) = &exe_dir {
+        portable |= dir.join(PORTABLE_MARKER).exists();
+    }
+
+    let mut paths = match (portable, exe_dir) {
+        (true, Some(dir)) => AppPaths {
+            config_file: dir.join("config.toml"),
+            data_dir: dir.join("data"),
+            cache_dir: dir.join("cache"),
+        },
+        _ => AppPaths {
+            config_file: config_home().join(APP_DIR_NAME).join("config.toml"),
+            data_dir: data_home().join(APP_DIR_NAME),
+            cache_dir: cache_home().join(APP_DIR_NAME),
+        },
+    };
+
+    if let Some(config_file) = config_override {
+        paths.config_file = config_file;
+    }
+
+    for dir in [&paths.data_dir, &paths.cache_dir] {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("Failed to create {}: {}", dir.display(), e);
+        }
+    }
+
+    if APP_PATHS.set(paths).is_err() {
+        eprintln!("App paths were already initialized");
+    }
+}
+
+/// Function to get the resolved locations
+///
+/// #Arguments
+/// - none
+///
+/// #Returns
+/// - the AppPaths from `init`, or the default locations if `init` was never called
+pub fn get() -> &'static AppPaths {
+    APP_PATHS.get_or_init(default_paths)
+}
+
+/// The locations used when `init` was never called
+#[cfg(not(test))]
+fn default_paths() -> AppPaths {
+    AppPaths {
+        config_file: config_home().join(APP_DIR_NAME).join("config.toml"),
+        data_dir: data_home().join(APP_DIR_NAME),
+        cache_dir: cache_home().join(APP_DIR_NAME),
+    }
+}
+
+/// Tests never call `init`, so everything they write goes to the system temp folder
+/// instead of the user's files
+#[cfg(test)]
+fn default_paths() -> AppPaths {
+    let root = env::temp_dir().join(format!("imp-porter-test-{}", std::process::id()));
+    let paths = AppPaths {
+        config_file: root.join("config.toml"),
+        data_dir: root.join("data"),
+        cache_dir: root.join("cache"),
+    };
+    for dir in [&paths.data_dir, &paths.cache_dir] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    paths
+}
+
+/// Folder where imp-orter stored its files before it used the XDG directories
+pub fn legacy_documents_dir() -> PathBuf {
+    env::current_dir().unwrap_or_default().join("documents")
+}
+
+/// $XDG_CONFIG_HOME, falling back to the platform's usual config folder
+fn config_home() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME").unwrap_or_else(|| {
+        if cfg!(target_os = "windows") {
+            env_dir("APPDATA")
+        } else if cfg!(target_os = "macos") {
+            home_dir().map(|home| home.join("Library/Application Support"))
+        } else {
+            home_dir().map(|home| home.join(".config"))
+        }
+        .unwrap_or_else(legacy_documents_dir)
+    })
+}
+
+/// $XDG_DATA_HOME, falling back to the platform's usual data folder
+fn data_home() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME").unwrap_or_else(|| {
+        if cfg!(target_os = "windows") {
+            env_dir("APPDATA")
+        } else if cfg!(target_os = "macos") {
+            home_dir().map(|home| home.join("Library/Application Support"))
+        } else {
+            home_dir().map(|home| home.join(".local/share"))
+        }
+        .unwrap_or_else(legacy_documents_dir)
+    })
+}
+
+/// $XDG_CACHE_HOME, falling back to the platform's usual cache folder
+fn cache_home() -> PathBuf {
+    xdg_dir("XDG_CACHE_HOME").unwrap_or_else(|| {
+        if cfg!(target_os = "windows") {
+            env_dir("LOCALAPPDATA")
+        } else if cfg!(target_os = "macos") {
+            home_dir().map(|home| home.join("Library/Caches"))
+        } else {
+            home_dir().map(|home| home.join(".cache"))
+        }
+        .unwrap_or_else(legacy_documents_dir)
+    })
+}
+
+/// XDG variables must hold an absolute path, anything else is ignored
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    env_dir(var).filter(|path| path.is_absolute())
+}
+
+/// Reads a folder from an environment variable, ignoring empty values
+fn env_dir(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The user's home folder
+fn home_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env_dir("USERPROFILE")
+    } else {
+        env_dir("HOME")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // each test sets variables only it reads, so tests running in parallel don't see them
+
+    #[test]
+    fn ignores_relative_xdg_dirs() {
+        let absolute = env::temp_dir().join("config");
+        env::set_var("IMP_PORTER_TEST_XDG_ABSOLUTE", &absolute);
+        env::set_var("IMP_PORTER_TEST_XDG_RELATIVE", "player/.config");
+
+        assert_eq!(xdg_dir("IMP_PORTER_TEST_XDG_ABSOLUTE"), Some(absolute));
+        assert_eq!(xdg_dir("IMP_PORTER_TEST_XDG_RELATIVE"), None);
+    }
+
+    #[test]
+    fn ignores_empty_and_missing_variables() {
+        env::set_var("IMP_PORTER_TEST_EMPTY", "");
+        env::remove_var("IMP_PORTER_TEST_MISSING");
+
+        assert_eq!(env_dir("IMP_PORTER_TEST_EMPTY"), None);
+        assert_eq!(env_dir("IMP_PORTER_TEST_MISSING"), None);
+    }
+}
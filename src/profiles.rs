@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+use crate::saves::{self, Owner};
+use crate::source_port::SourcePort;
+
+/// A source port config kept by imp-orter and passed to the port with -config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// The mod or preset the profile belongs to, None for the base profile
+    pub owner: Option<Owner>,
+    /// Path of the mod relative to the mods directory or name of the preset, empty for the
+    /// base profile
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Function to get the folder config profiles are kept in
+///
+/// #Arguments
+///  - none
+///
+/// #Returns
+/// - PathBuf inside the data directory
+///
+/// Every profile is a folder holding one config file per source port, named the way the
+/// port names it (gzdoom.ini, dsda-doom.cfg, ...)
+pub fn get_profiles_path() -> PathBuf {
+    paths::get().data_dir.join("profiles")
+}
+
+/// Function to get the base profile of a port, the one new profiles are cloned from
+///
+/// #Arguments
+/// - the SourcePort
+///
+/// #Returns
+/// - PathBuf of the config file, which may not exist yet
+pub fn base_profile(port: SourcePort) -> PathBuf {
+    get_profiles_path()
+        .join("base")
+        .join(port.config_file_name())
+}
+
+/// Function to get the profile of a mod or preset for a port
+///
+/// #Arguments
+/// - who the profile belongs to
+/// - path of the mod relative to the mods directory or name of the preset
+/// - the SourcePort
+///
+/// #Returns
+/// - PathBuf of the config file, which may not exist yet
+pub fn profile_path(owner: Owner, name: &str, port: SourcePort) -> PathBuf {
+    get_profiles_path()
+        .join(format!("{}s", owner.as_str()))
+        .join(saves::folder_name(name))
+        .join(port.config_file_name())
+}
+
+/// Function to find the profile to launch a mod or preset with
+///
+/// #Arguments
+/// - who the profile belongs to
+/// - path of the mod relative to the mods directory or name of the preset
+/// - the SourcePort
+///
+/// #Returns
+/// - PathBuf of the config file, or None if no profile was made, in which case the
+///   port uses its own config
+pub fn profile_for(owner: Owner, name: &str, port: SourcePort) -> Option<PathBuf> {
+    Some(profile_path(owner, name, port)).filter(|path| path.is_file())
+}
+
+/// Function to list the profiles of a port
+///
+/// #Arguments
+/// - the SourcePort
+///
+/// #Returns
+/// - Vector of Profiles, the base profile first even if it doesn't exist yet, then the
+///   mods and presets with a profile for the port, each sorted by name
+pub fn list_profiles(port: SourcePort) -> Vec<Profile> {
+    let mut profiles = vec![Profile {
+        owner: None,
+        name: String::new(),
+        path: base_profile(port),
+    }];
+
+    for owner in [Owner::Mod, Owner::Preset] {
+        let Ok(entries) = fs::read_dir(get_profiles_path().join(format!("{}s", owner.as_str())))
+        else {
+            continue;
+        };
+
+        let mut owned: Vec<Profile> = entries
+            .flatten()
+            .map(|entry| entry.path().join(port.config_file_name()))
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let name = saves::name_of_folder(&path.parent()?.file_name()?.to_string_lossy());
+                Some(Profile {
+                    owner: Some(owner),
+                    name,
+                    path,
+                })
+            })
+            .collect();
+        owned.sort_by_key(|profile| profile.name.to_lowercase());
+        profiles.extend(owned);
+    }
+    profiles
+}
+
+/// Function to copy a config into a profile, replacing what the profile had
+///
+/// #Arguments
+/// - the config to copy, a missing file gives an empty profile the port fills with its defaults
+/// - path of the profile to write
+///
+/// #Returns
+/// - an io error if the profile can't be written
+///
+/// Used to clone the base profile, copy one profile to another mod or preset, reset a
+/// profile to the base and import an existing config as the base
+pub fn copy_profile(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if from.is_file() {
+        fs::copy(from, to).map(|_| ())
+    } else {
+        fs::write(to, "")
+    }
+}
+
+/// Function to compare a profile with the base profile of its port
+///
+/// #Arguments
+/// - path of the profile
+/// - the SourcePort
+///
+/// #Returns
+/// - Vector of lines: a "[section]" header before the changes in each section, then
+///   "- line" for settings only the base has and "+ line" for settings only the profile has
+/// - an io error if the profile can't be read
+///
+/// A setting that was changed shows up as one removed and one added line. Blank lines and
+/// comments are ignored.
+pub fn diff_with_base(path: &Path, port: SourcePort) -> io::Result<Vec<String>> {
+    let profile = fs::read_to_string(path)?;
+    let base = fs::read_to_string(base_profile(port)).unwrap_or_default();
+
+    let base_lines = keyed_lines(&base);
+    let profile_lines = keyed_lines(&profile);
+    let in_base: HashSet<&(String, String)> = base_lines.iter().collect();
+    let in_profile: HashSet<&(String, String)> = profile_lines.iter().collect();
+
+    // sections in the order they first appear, so the diff reads like the files
+    let mut sections: Vec<&str> = Vec::new();
+    for (section, _) in base_lines.iter().chain(&profile_lines) {
+        if !sections.contains(&section.as_str()) {
+            sections.push(section);
+        }
+    }
+
+    let mut diff = Vec::new();
+    for section in sections {
+        let removed = base_lines
+            .iter()
+            .filter(|line| line.0 == section && !in_profile.contains(line))
+            .map(|(_, line)| format!("- {}", line));
+        let added = profile_lines
+            .iter()
+            .filter(|line| line.0 == section && !in_base.contains(line))
+            .map(|(_, line)| format!("+ {}", line));
+        let changes: Vec<String> = removed.chain(added).collect();
+
+        if !changes.is_empty() {
+            if !section.is_empty() {
+                diff.push(format!("[{}]", section));
+            }
+            diff.extend(changes);
+        }
+    }
+    Ok(diff)
+}
+
+/// Splits a config into its settings, each paired with the [section] it is in.
+/// cfg files have no sections, so all their settings are in the "" section.
+fn keyed_lines(contents: &str) -> Vec<(String, String)> {
+    let mut section = String::new();
+    let mut lines = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = name.to_string();
+            continue;
+        }
+        lines.push((section.clone(), line.to_string()));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_settings_by_section() {
+        let ini = "# written by gzdoom\n\
+                   [GlobalSettings]\n\
+                   fullscreen=true\n\
+                   \n\
+                   ; comment\n\
+                   [Doom.Player]\n\
+                   name=Player\n";
+        assert_eq!(
+            keyed_lines(ini),
+            vec![
+                ("GlobalSettings".to_string(), "fullscreen=true".to_string()),
+                ("Doom.Player".to_string(), "name=Player".to_string()),
+            ]
+        );
+
+        let cfg = "mouse_sensitivity 5\n  key_right 77  \n";
+        assert_eq!(
+            keyed_lines(cfg),
+            vec![
+                (String::new(), "mouse_sensitivity 5".to_string()),
+                (String::new(), "key_right 77".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_a_profile_with_the_base() {
+        let port = SourcePort::GZDoom;
+        fs::create_dir_all(base_profile(port).parent().unwrap()).unwrap();
+        fs::write(
+            base_profile(port),
+            "[GlobalSettings]\nfullscreen=true\nvid_vsync=false\n[Doom.Player]\nname=Player\n",
+        )
+        .unwrap();
+
+        let profile = profile_path(Owner::Mod, "megawads/sunlust.wad", port);
+        copy_profile(&base_profile(port), &profile).unwrap();
+        assert_eq!(
+            profile_for(Owner::Mod, "megawads/sunlust.wad", port),
+            Some(profile.clone())
+        );
+        assert!(diff_with_base(&profile, port).unwrap().is_empty());
+
+        fs::write(
+            &profile,
+            "[GlobalSettings]\nfullscreen=true\nvid_vsync=true\n[Doom.Player]\nname=Player\n\
+             [Doom.ConsoleVariables]\nsv_fastmonsters=true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            diff_with_base(&profile, port).unwrap(),
+            vec![
+                "[GlobalSettings]",
+                "- vid_vsync=false",
+                "+ vid_vsync=true",
+                "[Doom.ConsoleVariables]",
+                "+ sv_fastmonsters=true",
+            ]
+        );
+
+        let listed: Vec<(Option<Owner>, String)> = list_profiles(port)
+            .into_iter()
+            .map(|profile| (profile.owner, profile.name))
+            .collect();
+        assert!(listed.contains(&(None, String::new())));
+        assert!(listed.contains(&(Some(Owner::Mod), "megawads/sunlust.wad".to_string())));
+        assert!(diff_with_base(&get_profiles_path().join("missing.ini"), port).is_err());
+    }
+}
@@ -0,0 +1,424 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::compat::CompatLevel;
+use crate::config_functions::ModSettings;
+use crate::library::LibraryFile;
+use crate::utility::ModKind;
+
+/// Orders the mod list can be shown in. Everything except Name puts the biggest or most
+/// recent first, files that tie are ordered by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Name,
+    DateAdded,
+    LastPlayed,
+    Size,
+    MapCount,
+}
+
+impl SortKey {
+    /// Every order, as listed in the UI
+    pub const ALL: [SortKey; 5] = [
+        SortKey::Name,
+        SortKey::DateAdded,
+        SortKey::LastPlayed,
+        SortKey::Size,
+        SortKey::MapCount,
+    ];
+
+    /// Label used in the UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::DateAdded => "Date added",
+            SortKey::LastPlayed => "Last played",
+            SortKey::Size => "Size",
+            SortKey::MapCount => "Map count",
+        }
+    }
+
+    /// Function to read an order from its label
+    ///
+    /// #Arguments
+    /// - the label, case-insensitive, with spaces, '-' or '_' between words ("date-added")
+    ///
+    /// #Returns
+    /// - the SortKey, or None if the label is unknown
+    pub fn from_label(label: &str) -> Option<SortKey> {
+        let label = label.trim().to_lowercase().replace(['-', '_'], " ");
+        SortKey::ALL
+            .into_iter()
+            .find(|key| key.label().to_lowercase() == label)
+    }
+
+    /// Compares two files in this order
+    fn compare(&self, a: &LibraryFile, b: &LibraryFile) -> Ordering {
+        let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+        match self {
+            SortKey::Name => by_name(),
+            SortKey::DateAdded => b.imported_at.cmp(&a.imported_at).then_with(by_name),
+            // files that were never played have no date and sort last
+            SortKey::LastPlayed => b.last_played.cmp(&a.last_played).then_with(by_name),
+            SortKey::Size => b.size.cmp(&a.size).then_with(by_name),
+            SortKey::MapCount => b
+                .entry
+                .maps
+                .len()
+                .cmp(&a.entry.maps.len())
+                .then_with(by_name),
+        }
+    }
+}
+
+/// What to show in the mod list. Filters that are None let every file through.
+#[derive(Debug, Clone, Default)]
+pub struct ModQuery {
+    /// Text to find in the file name, folder, title or author, case-insensitive
+    pub search: String,
+    pub kind: Option<ModKind>,
+    /// IWAD file name such as "doom2.wad", case-insensitive
+    pub iwad: Option<String>,
+    pub tag: Option<String>,
+    pub compat: Option<CompatLevel>,
+    pub sort: SortKey,
+}
+
+/// The values each filter can be set to, taken from the files in the library
+#[derive(Debug, Clone, Default)]
+pub struct FilterOptions {
+    pub kinds: Vec<ModKind>,
+    pub iwads: Vec<String>,
+    pub tags: Vec<String>,
+    pub compat: Vec<CompatLevel>,
+}
+
+impl ModQuery {
+    /// Function to filter and sort the files of the library
+    ///
+    /// #Arguments
+    /// - the files from `Library::scan` or `Library::files`
+    /// - the per-mod settings from the config, for the IWAD each mod was last played with
+    ///
+    /// #Returns
+    /// - Vector of copies of the matching files in the query's order
+    pub fn run(
+        &self,
+        files: &[LibraryFile],
+        mods: &BTreeMap<String, ModSettings>,
+    ) -> Vec<LibraryFile> {
+        let mut matching: Vec<LibraryFile> = files
+            .iter()
+            .filter(|file| self.matches(file, mods))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| self.sort.compare(a, b));
+        matching
+    }
+
+    /// Checks a file against every filter and the search text
+    fn matches(&self, file: &LibraryFile, mods: &BTreeMap<String, ModSettings>) -> bool {
+        if self.kind.is_some_and(|kind| kind != file.entry.kind) {
+            return false;
+        }
+        if self
+            .compat
+            .is_some_and(|compat| compat != file.entry.compat)
+        {
+            return false;
+        }
+        if let Some(iwad) = &self.iwad {
+            if !iwads_for(file, mods).contains(&iwad.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(tag) = &self.tag {
+            if !file
+                .user
+                .tags
+                .iter()
+                .any(|file_tag| file_tag.eq_ignore_ascii_case(tag))
+            {
+                return false;
+            }
+        }
+
+        let search = self.search.trim().to_lowercase();
+        if search.is_empty() {
+            return true;
+        }
+        let info = file.entry.info.as_ref();
+        [
+            Some(&file.name),
+            Some(&file.entry.category),
+            info.and_then(|info| info.title.as_ref()),
+            info.and_then(|info| info.author.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|text| text.to_lowercase().contains(&search))
+    }
+}
+
+/// Function to collect the values the filters can be set to
+///
+/// #Arguments
+/// - the files from `Library::scan` or `Library::files`
+/// - the per-mod settings from the config
+///
+/// #Returns
+/// - FilterOptions with each value used by at least one file, in a fixed order
+pub fn filter_options(
+    files: &[LibraryFile],
+    mods: &BTreeMap<String, ModSettings>,
+) -> FilterOptions {
+    let kinds: BTreeSet<usize> = files
+        .iter()
+        .filter_map(|file| {
+            ModKind::ALL
+                .iter()
+                .position(|kind| *kind == file.entry.kind)
+        })
+        .collect();
+    let iwads: BTreeSet<String> = files
+        .iter()
+        .flat_map(|file| iwads_for(file, mods))
+        .collect();
+    let tags: BTreeSet<String> = files
+        .iter()
+        .flat_map(|file| file.user.tags.clone())
+        .collect();
+    let compat: BTreeSet<CompatLevel> = files.iter().map(|file| file.entry.compat).collect();
+
+    FilterOptions {
+        kinds: kinds.into_iter().map(|index| ModKind::ALL[index]).collect(),
+        iwads: iwads.into_iter().collect(),
+        tags: tags.into_iter().collect(),
+        compat: compat.into_iter().collect(),
+    }
+}
+
+/// IWAD file names a mod is meant for, in lowercase: the one its GAMEINFO asks for and
+/// the one it was last played with
+fn iwads_for(file: &LibraryFile, mods: &BTreeMap<String, ModSettings>) -> Vec<String> {
+    let played = mods
+        .get(&file.entry.key())
+        .and_then(|settings| settings.iwad.as_deref())
+        .and_then(|iwad| Path::new(iwad).file_name())
+        .map(|name| name.to_string_lossy().into_owned());
+
+    let mut iwads: Vec<String> = [file.entry.required_iwad.clone(), played]
+        .into_iter()
+        .flatten()
+        .map(|iwad| iwad.to_lowercase())
+        .collect();
+    iwads.dedup();
+    iwads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idgames::ModInfo;
+    use crate::library::UserData;
+    use crate::utility::ModEntry;
+
+    /// A library file in the mods folder /mods, in the given subfolder
+    fn file(category: &str, name: &str, kind: ModKind, maps: usize) -> LibraryFile {
+        let key = crate::utility::mod_key(category, name);
+        LibraryFile {
+            name: name.to_string(),
+            entry: ModEntry {
+                path: format!("/mods/{}", key),
+                kind,
+                maps: (1..=maps).map(|map| format!("MAP{:02}", map)).collect(),
+                required_iwad: None,
+                info: None,
+                compat: CompatLevel::Vanilla,
+                category: category.to_string(),
+                lumps: Vec::new(),
+            },
+            size: 0,
+            hash: key,
+            source_archive: None,
+            imported_at: 0,
+            play_count: 0,
+            last_played: None,
+            user: UserData::default(),
+        }
+    }
+
+    fn library() -> Vec<LibraryFile> {
+        let mut sunlust = file("megawads", "sunlust.wad", ModKind::Wad, 32);
+        sunlust.entry.compat = CompatLevel::Boom;
+        sunlust.size = 30_000_000;
+        sunlust.imported_at = 100;
+        sunlust.last_played = Some(500);
+        sunlust.entry.info = Some(ModInfo {
+            title: Some("Sunlust".to_string()),
+            author: Some("Ribbiks and dannebubinga".to_string()),
+            ..ModInfo::default()
+        });
+
+        let mut brutal = file("", "brutal.pk3", ModKind::Pk3, 0);
+        brutal.entry.compat = CompatLevel::Zdoom;
+        brutal.entry.required_iwad = Some("doom2.wad".to_string());
+        brutal.size = 10_000_000;
+        brutal.imported_at = 300;
+
+        let mut scythe = file("", "Scythe.wad", ModKind::Wad, 32);
+        scythe.size = 2_000_000;
+        scythe.imported_at = 200;
+        scythe.last_played = Some(900);
+
+        vec![
+            sunlust,
+            brutal,
+            scythe,
+            file("", "fix.deh", ModKind::Deh, 0),
+        ]
+    }
+
+    fn names(files: &[LibraryFile]) -> Vec<&str> {
+        files.iter().map(|file| file.name.as_str()).collect()
+    }
+
+    fn run(query: ModQuery) -> Vec<String> {
+        names(&query.run(&library(), &BTreeMap::new()))
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_each_key() {
+        let sorted = |sort| {
+            run(ModQuery {
+                sort,
+                ..ModQuery::default()
+            })
+        };
+
+        assert_eq!(
+            sorted(SortKey::Name),
+            ["brutal.pk3", "fix.deh", "Scythe.wad", "sunlust.wad"]
+        );
+        assert_eq!(
+            sorted(SortKey::DateAdded),
+            ["brutal.pk3", "Scythe.wad", "sunlust.wad", "fix.deh"]
+        );
+        assert_eq!(
+            sorted(SortKey::LastPlayed),
+            ["Scythe.wad", "sunlust.wad", "brutal.pk3", "fix.deh"]
+        );
+        assert_eq!(
+            sorted(SortKey::Size),
+            ["sunlust.wad", "brutal.pk3", "Scythe.wad", "fix.deh"]
+        );
+        assert_eq!(
+            sorted(SortKey::MapCount),
+            ["Scythe.wad", "sunlust.wad", "brutal.pk3", "fix.deh"]
+        );
+    }
+
+    #[test]
+    fn searches_names_folders_titles_and_authors() {
+        let search = |text: &str| {
+            run(ModQuery {
+                search: text.to_string(),
+                ..ModQuery::default()
+            })
+        };
+
+        assert_eq!(search("SCYTHE"), ["Scythe.wad"]);
+        assert_eq!(search("megawads"), ["sunlust.wad"]);
+        assert_eq!(search("ribbiks"), ["sunlust.wad"]);
+        assert_eq!(search("  "), run(ModQuery::default()));
+        assert!(search("plutonia").is_empty());
+    }
+
+    #[test]
+    fn filters_by_kind_and_compat() {
+        let kind = run(ModQuery {
+            kind: Some(ModKind::Wad),
+            ..ModQuery::default()
+        });
+        assert_eq!(kind, ["Scythe.wad", "sunlust.wad"]);
+
+        let compat = run(ModQuery {
+            compat: Some(CompatLevel::Zdoom),
+            ..ModQuery::default()
+        });
+        assert_eq!(compat, ["brutal.pk3"]);
+    }
+
+    #[test]
+    fn filters_by_the_iwad_asked_for_or_last_played() {
+        let mut mods = BTreeMap::new();
+        mods.insert(
+            "megawads/sunlust.wad".to_string(),
+            ModSettings {
+                iwad: Some("/games/DOOM2.WAD".to_string()),
+                port: None,
+            },
+        );
+        // settings kept under the bare file name belong to a mod at the top of the folder
+        mods.insert(
+            "sunlust.wad".to_string(),
+            ModSettings {
+                iwad: Some("/games/tnt.wad".to_string()),
+                port: None,
+            },
+        );
+        let query = ModQuery {
+            iwad: Some("Doom2.wad".to_string()),
+            ..ModQuery::default()
+        };
+
+        assert_eq!(
+            names(&query.run(&library(), &mods)),
+            ["brutal.pk3", "sunlust.wad"]
+        );
+        assert_eq!(filter_options(&library(), &mods).iwads, ["doom2.wad"]);
+    }
+
+    #[test]
+    fn lists_the_values_in_use() {
+        let options = filter_options(&library(), &BTreeMap::new());
+        assert_eq!(options.kinds, [ModKind::Wad, ModKind::Pk3, ModKind::Deh]);
+        assert_eq!(options.iwads, ["doom2.wad"]);
+        assert_eq!(
+            options.compat,
+            [CompatLevel::Vanilla, CompatLevel::Boom, CompatLevel::Zdoom]
+        );
+    }
+
+    #[test]
+    fn reads_sort_labels() {
+        assert_eq!(SortKey::from_label("date-added"), Some(SortKey::DateAdded));
+        assert_eq!(SortKey::from_label(" Map count "), Some(SortKey::MapCount));
+        assert_eq!(SortKey::from_label("rating"), None);
+    }
+
+    #[test]
+    fn filters_by_user_tag() {
+        let mut files = library();
+        files[0].user.tags = vec!["Slaughter".to_string(), "megawad".to_string()];
+        files[2].user.tags = vec!["megawad".to_string()];
+        let query = ModQuery {
+            tag: Some("MEGAWAD".to_string()),
+            ..ModQuery::default()
+        };
+
+        assert_eq!(
+            names(&query.run(&files, &BTreeMap::new())),
+            ["Scythe.wad", "sunlust.wad"]
+        );
+        assert_eq!(
+            filter_options(&files, &BTreeMap::new()).tags,
+            ["Slaughter", "megawad"]
+        );
+    }
+}
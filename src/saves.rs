@@ -0,0 +1,285 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::paths;
+
+/// Who a save folder or config profile belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Owner {
+    Mod,
+    Preset,
+}
+
+impl Owner {
+    /// Lowercase label used in the UI. With an "s" added it names the folder the owners
+    /// are kept in
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Owner::Mod => "mod",
+            Owner::Preset => "preset",
+        }
+    }
+
+    /// Function to read an owner from its label
+    ///
+    /// #Arguments
+    /// - the label, "mod" or "preset"
+    ///
+    /// #Returns
+    /// - the Owner, or None if the label is unknown
+    pub fn from_label(label: &str) -> Option<Owner> {
+        [Owner::Mod, Owner::Preset]
+            .into_iter()
+            .find(|owner| owner.as_str() == label.trim())
+    }
+
+    /// Folder inside the saves folder with one subfolder per owner
+    fn dir(&self) -> PathBuf {
+        get_saves_path().join(format!("{}s", self.as_str()))
+    }
+}
+
+/// The saves of one mod or preset
+#[derive(Debug, Clone, Serialize)]
+pub struct SaveFolder {
+    /// Path of the mod relative to the mods directory or name of the preset
+    pub name: String,
+    pub owner: Owner,
+    pub path: PathBuf,
+    /// Saves in the folder, newest first
+    pub saves: Vec<SaveFile>,
+}
+
+/// A single save written by a source port
+#[derive(Debug, Clone, Serialize)]
+pub struct SaveFile {
+    pub name: String,
+    pub size: u64,
+    /// When the save was written, in seconds since the Unix epoch
+    pub modified: i64,
+}
+
+impl SaveFile {
+    /// When the save was written in local time, e.g. "2024-05-01 21:30"
+    pub fn modified_label(&self) -> String {
+        DateTime::from_timestamp(self.modified, 0)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Function to get the folder saves are kept in, with one subfolder per mod and per preset
+///
+/// #Arguments
+///  - none
+///
+/// #Returns
+/// - PathBuf inside the data directory
+pub fn get_saves_path() -> PathBuf {
+    paths::get().data_dir.join("saves")
+}
+
+/// Function to get the save folder of a mod
+///
+/// #Arguments
+/// - path of the mod relative to the mods directory, the first file of the load order when
+///   several are played together
+///
+/// #Returns
+/// - PathBuf of the folder, which may not exist yet
+pub fn mod_save_dir(mod_name: &str) -> PathBuf {
+    Owner::Mod.dir().join(folder_name(mod_name))
+}
+
+/// Function to get the save folder of a preset
+///
+/// #Arguments
+/// - name of the preset
+///
+/// #Returns
+/// - PathBuf of the folder, which may not exist yet
+pub fn preset_save_dir(preset_name: &str) -> PathBuf {
+    Owner::Preset.dir().join(folder_name(preset_name))
+}
+
+/// Function to list the saves of every mod and preset
+///
+/// #Arguments
+///  - none
+///
+/// #Returns
+/// - Vector of SaveFolders with at least one save, mods first, each sorted by name
+pub fn list_saves() -> Vec<SaveFolder> {
+    let mut folders = Vec::new();
+    for owner in [Owner::Mod, Owner::Preset] {
+        let Ok(entries) = fs::read_dir(owner.dir()) else {
+            continue;
+        };
+
+        let mut owned: Vec<SaveFolder> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .map(|path| SaveFolder {
+                name: path
+                    .file_name()
+                    .map(|name| name_of_folder(&name.to_string_lossy()))
+                    .unwrap_or_default(),
+                owner,
+                saves: saves_in(&path),
+                path,
+            })
+            .filter(|folder| !folder.saves.is_empty())
+            .collect();
+        owned.sort_by_key(|folder| folder.name.to_lowercase());
+        folders.extend(owned);
+    }
+    folders
+}
+
+/// Lists the files in a save folder, newest first
+fn saves_in(dir: &Path) -> Vec<SaveFile> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut saves: Vec<SaveFile> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_secs() as i64)
+                .unwrap_or_default();
+            Some(SaveFile {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                size: metadata.len(),
+                modified,
+            })
+        })
+        .collect();
+    saves.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    saves
+}
+
+/// Function to turn a mod path or preset name into a folder name every platform accepts
+///
+/// #Arguments
+/// - the name, a mod's path relative to the mods directory or a preset name
+///
+/// #Returns
+/// - the name with '/' replaced by '+' and other characters that aren't allowed in file
+///   names replaced by '_'
+///
+/// '+' is never kept from the name itself, so "maps/scythe.wad" and "maps_scythe.wad" get
+/// different folders and `name_of_folder` can turn the folder back into the path
+pub fn folder_name(name: &str) -> String {
+    let cleaned: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c == '/' {
+                '+'
+            } else if c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // "." and ".." would point outside the folder, and Windows drops trailing dots and spaces
+    let cleaned = cleaned.trim_end_matches(['.', ' ']);
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Function to get the name shown for a folder made by `folder_name`
+///
+/// #Arguments
+/// - name of the folder
+///
+/// #Returns
+/// - the name with '+' turned back into '/'
+pub fn name_of_folder(folder: &str) -> String {
+    folder.replace('+', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mods_in_subfolders_get_their_own_folder() {
+        assert_eq!(folder_name("scythe.wad"), "scythe.wad");
+        assert_eq!(folder_name("maps/scythe.wad"), "maps+scythe.wad");
+        assert_ne!(
+            folder_name("maps/scythe.wad"),
+            folder_name("maps_scythe.wad")
+        );
+        assert_ne!(
+            folder_name("maps/scythe.wad"),
+            folder_name("maps+scythe.wad")
+        );
+        assert_eq!(
+            name_of_folder(&folder_name("maps/megawads/scythe.wad")),
+            "maps/megawads/scythe.wad"
+        );
+    }
+
+    #[test]
+    fn makes_names_safe_for_folders() {
+        assert_eq!(folder_name("  Sunlust (v1.1).wad "), "Sunlust _v1.1_.wad");
+        assert_eq!(folder_name("What: a *preset*?"), "What_ a _preset__");
+        assert_eq!(folder_name("Épisode 1"), "Épisode 1");
+        // names that would point outside the saves folder
+        assert_eq!(folder_name(".."), "_");
+        assert_eq!(folder_name(""), "_");
+        assert_eq!(folder_name("../../etc"), "..+..+etc");
+        assert_eq!(folder_name("preset. "), "preset");
+    }
+
+    #[test]
+    fn lists_saves_newest_first() {
+        let dir =
+            std::env::temp_dir().join(format!("imp-porter-saves-list-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, age) in [("old.zds", 120), ("new.zds", 0), ("also-new.zds", 0)] {
+            let path = dir.join(name);
+            fs::write(&path, name).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+
+        // saves written in the same second are ordered by name
+        let saves: Vec<String> = saves_in(&dir).into_iter().map(|save| save.name).collect();
+        assert_eq!(saves, ["also-new.zds", "new.zds", "old.zds"]);
+        assert!(saves_in(&dir.join("missing")).is_empty());
+    }
+}
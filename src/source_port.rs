@@ -0,0 +1,354 @@
+use std::path::Path;
+
+use crate::utility::ModKind;
+
+/// The source ports imp-orter knows how to launch. Each one has its own
+/// command line conventions and supports a different set of mod formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourcePort {
+    GZDoom,
+    LZDoom,
+    DsdaDoom,
+    PrBoomPlus,
+    Woof,
+    CrispyDoom,
+    ChocolateDoom,
+    Eternity,
+    Zandronum,
+    Odamex,
+}
+
+impl SourcePort {
+    /// Every supported port, in the order they are offered to the user
+    pub const ALL: [SourcePort; 10] = [
+        SourcePort::GZDoom,
+        SourcePort::LZDoom,
+        SourcePort::DsdaDoom,
+        SourcePort::PrBoomPlus,
+        SourcePort::Woof,
+        SourcePort::CrispyDoom,
+        SourcePort::ChocolateDoom,
+        SourcePort::Eternity,
+        SourcePort::Zandronum,
+        SourcePort::Odamex,
+    ];
+
+    /// Display name of the port
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourcePort::GZDoom => "GZDoom",
+            SourcePort::LZDoom => "LZDoom",
+            SourcePort::DsdaDoom => "dsda-doom",
+            SourcePort::PrBoomPlus => "PrBoom+",
+            SourcePort::Woof => "Woof!",
+            SourcePort::CrispyDoom => "Crispy Doom",
+            SourcePort::ChocolateDoom => "Chocolate Doom",
+            SourcePort::Eternity => "Eternity",
+            SourcePort::Zandronum => "Zandronum",
+            SourcePort::Odamex => "Odamex",
+        }
+    }
+
+    /// Lowercase executable names (without extension) the port ships with
+    fn executable_names(&self) -> &'static [&'static str] {
+        match self {
+            SourcePort::GZDoom => &["gzdoom"],
+            SourcePort::LZDoom => &["lzdoom"],
+            SourcePort::DsdaDoom => &["dsda-doom"],
+            SourcePort::PrBoomPlus => &["prboom-plus", "glboom-plus"],
+            SourcePort::Woof => &["woof"],
+            SourcePort::CrispyDoom => &["crispy-doom"],
+            SourcePort::ChocolateDoom => &["chocolate-doom"],
+            SourcePort::Eternity => &["eternity"],
+            SourcePort::Zandronum => &["zandronum"],
+            SourcePort::Odamex => &["odamex"],
+        }
+    }
+
+    /// Function to recognize a source port from its executable
+    ///
+    /// #Arguments
+    /// - path to the executable
+    ///
+    /// #Returns
+    /// - the SourcePort, or None if the executable is not a supported port
+    ///
+    /// Matching is on the file name without extension, so GZDoom, gzdoom.exe
+    /// and GZDoom.app are all recognized.
+    pub fn from_executable<P: AsRef<Path>>(path: P) -> Option<SourcePort> {
+        let stem = path.as_ref().file_stem()?.to_str()?.to_lowercase();
+        SourcePort::ALL
+            .into_iter()
+            .find(|port| port.executable_names().contains(&stem.as_str()))
+    }
+
+    /// The ZDoom family shares GZDoom's command line and file support
+    fn is_zdoom_family(&self) -> bool {
+        matches!(
+            self,
+            SourcePort::GZDoom | SourcePort::LZDoom | SourcePort::Zandronum
+        )
+    }
+
+    /// Checks whether the port can load a kind of mod file
+    ///
+    /// #Arguments
+    /// - the kind of mod file
+    ///
+    /// #Returns
+    /// - true if the port can load it
+    pub fn supports_kind(&self, kind: ModKind) -> bool {
+        match kind {
+            ModKind::Wad | ModKind::Deh | ModKind::Bex => true,
+            ModKind::Pk3 => self.is_zdoom_family() || *self == SourcePort::Eternity,
+            ModKind::Pk7 | ModKind::Ipk3 => self.is_zdoom_family(),
+        }
+    }
+
+    /// Builds the arguments to start the game on a given map
+    ///
+    /// #Arguments
+    /// - map name as a string reference
+    ///
+    /// #Returns
+    /// - "-warp xx" for MAPxx, "-warp x y" for ExMy
+    /// - "+map NAME" for custom map names on ports that support it
+    /// - None if the port has no way to start on that map
+    pub fn warp_args(&self, map: &str) -> Option<Vec<String>> {
+        let upper = map.to_uppercase();
+        let bytes = upper.as_bytes();
+
+        if let Some(number) = upper.strip_prefix("MAP") {
+            if number.len() == 2 && number.chars().all(|c| c.is_ascii_digit()) {
+                return Some(vec!["-warp".to_string(), number.to_string()]);
+            }
+        }
+
+        if bytes.len() == 4
+            && bytes[0] == b'E'
+            && bytes[2] == b'M'
+            && bytes[1].is_ascii_digit()
+            && bytes[3].is_ascii_digit()
+        {
+            return Some(vec![
+                "-warp".to_string(),
+                (bytes[1] as char).to_string(),
+                (bytes[3] as char).to_string(),
+            ]);
+        }
+
+        // only ports with a console accept +map for custom names
+        if self.is_zdoom_family() || *self == SourcePort::Odamex {
+            return Some(vec!["+map".to_string(), upper]);
+        }
+
+        None
+    }
+
+    /// Builds the arguments to keep saves in a given folder
+    ///
+    /// #Arguments
+    /// - path to the save folder
+    ///
+    /// #Returns
+    /// - "-savedir DIR" for the ZDoom family and the Chocolate Doom family
+    /// - "-save DIR" for the Boom family
+    /// - None if the port has no way to move its saves
+    pub fn save_dir_args(&self, dir: &Path) -> Option<Vec<String>> {
+        let flag = match self {
+            SourcePort::GZDoom
+            | SourcePort::LZDoom
+            | SourcePort::Zandronum
+            | SourcePort::ChocolateDoom
+            | SourcePort::CrispyDoom => "-savedir",
+            SourcePort::DsdaDoom | SourcePort::PrBoomPlus | SourcePort::Woof => "-save",
+            SourcePort::Eternity | SourcePort::Odamex => return None,
+        };
+        Some(vec![flag.to_string(), dir.to_string_lossy().into_owned()])
+    }
+
+    /// File name the port gives its config, e.g. "gzdoom.ini". The ZDoom family writes
+    /// ini files and the other ports cfg files
+    pub fn config_file_name(&self) -> String {
+        let extension = if self.is_zdoom_family() { "ini" } else { "cfg" };
+        format!("{}.{}", self.executable_names()[0], extension)
+    }
+
+    /// Builds the arguments to read and write the config from a given file
+    ///
+    /// #Arguments
+    /// - path to the config file
+    ///
+    /// #Returns
+    /// - "-config FILE", which every supported port understands
+    pub fn config_args(&self, file: &Path) -> Vec<String> {
+        vec!["-config".to_string(), file.to_string_lossy().into_owned()]
+    }
+
+    /// Builds the full argument list for a launch
+    ///
+    /// #Arguments
+    /// - path to the IWAD as a string reference
+    /// - paths to the mod files in load order
+    /// - optional map to start on
+    /// - optional skill level from 1 to 5
+    ///
+    /// #Returns
+    /// - Vector of arguments to pass to the port
+    /// - an error message if the port can't load one of the files or start on the map
+    pub fn build_args(
+        &self,
+        wad_path: &str,
+        mod_paths: &[String],
+        start_map: Option<&str>,
+        skill: Option<u8>,
+    ) -> Result<Vec<String>, String> {
+        let mut args = vec!["-iwad".to_string(), wad_path.to_string()];
+        let mut files = Vec::new();
+        let mut patches = Vec::new();
+
+        for mod_path in mod_paths {
+            match ModKind::from_path(mod_path) {
+                Some(kind) if !self.supports_kind(kind) => {
+                    return Err(format!(
+                        "{} cannot load .{} files ({})",
+                        self.name(),
+                        kind.as_str(),
+                        mod_path
+                    ));
+                }
+                // DeHackEd patches go to -deh, everything else is loaded with -file
+                Some(kind) if kind.is_dehacked() => patches.push(mod_path.clone()),
+                _ => files.push(mod_path.clone()),
+            }
+        }
+
+        if !files.is_empty() {
+            args.push("-file".to_string());
+            args.extend(files);
+        }
+
+        // one -deh with every patch in load order. Chocolate Doom and the Boom family only
+        // read the files after the first -deh
+        if !patches.is_empty() {
+            args.push("-deh".to_string());
+            args.extend(patches);
+        }
+
+        if let Some(map) = start_map {
+            let warp = self
+                .warp_args(map)
+                .ok_or_else(|| format!("{} cannot start on map {}", self.name(), map))?;
+            args.extend(warp);
+        }
+
+        if let Some(skill) = skill {
+            args.push("-skill".to_string());
+            args.push(skill.to_string());
+        }
+
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn recognizes_executables() {
+        assert_eq!(
+            SourcePort::from_executable("/usr/bin/gzdoom"),
+            Some(SourcePort::GZDoom)
+        );
+        assert_eq!(
+            SourcePort::from_executable("/games/GLBoom-Plus.exe"),
+            Some(SourcePort::PrBoomPlus)
+        );
+        assert_eq!(SourcePort::from_executable("/usr/bin/doom"), None);
+    }
+
+    #[test]
+    fn passes_every_patch_to_one_deh() {
+        let mods = strings(&["a.deh", "maps.wad", "b.bex", "c.deh"]);
+        let args = SourcePort::ChocolateDoom
+            .build_args("doom2.wad", &mods, None, None)
+            .unwrap();
+        assert_eq!(
+            args,
+            strings(&[
+                "-iwad",
+                "doom2.wad",
+                "-file",
+                "maps.wad",
+                "-deh",
+                "a.deh",
+                "b.bex",
+                "c.deh"
+            ])
+        );
+    }
+
+    #[test]
+    fn adds_warp_and_skill() {
+        let mods = strings(&["maps.wad"]);
+        let args = SourcePort::DsdaDoom
+            .build_args("doom.wad", &mods, Some("e2m3"), Some(4))
+            .unwrap();
+        assert_eq!(
+            args,
+            strings(&["-iwad", "doom.wad", "-file", "maps.wad", "-warp", "2", "3", "-skill", "4"])
+        );
+    }
+
+    #[test]
+    fn rejects_files_the_port_cannot_load() {
+        let mods = strings(&["mod.pk3"]);
+        assert!(SourcePort::PrBoomPlus
+            .build_args("doom2.wad", &mods, None, None)
+            .is_err());
+        assert!(SourcePort::GZDoom
+            .build_args("doom2.wad", &mods, None, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn builds_warp_args() {
+        assert_eq!(
+            SourcePort::Woof.warp_args("MAP07"),
+            Some(strings(&["-warp", "07"]))
+        );
+        assert_eq!(
+            SourcePort::Woof.warp_args("e1m8"),
+            Some(strings(&["-warp", "1", "8"]))
+        );
+        assert_eq!(
+            SourcePort::GZDoom.warp_args("Intro"),
+            Some(strings(&["+map", "INTRO"]))
+        );
+        assert_eq!(SourcePort::ChocolateDoom.warp_args("INTRO"), None);
+    }
+
+    #[test]
+    fn moves_saves_with_the_port_flag() {
+        let dir = Path::new("/data/saves/mods/sunlust.wad");
+        assert_eq!(
+            SourcePort::GZDoom.save_dir_args(dir),
+            Some(strings(&["-savedir", "/data/saves/mods/sunlust.wad"]))
+        );
+        assert_eq!(
+            SourcePort::CrispyDoom.save_dir_args(dir),
+            Some(strings(&["-savedir", "/data/saves/mods/sunlust.wad"]))
+        );
+        assert_eq!(
+            SourcePort::DsdaDoom.save_dir_args(dir),
+            Some(strings(&["-save", "/data/saves/mods/sunlust.wad"]))
+        );
+        assert_eq!(SourcePort::Eternity.save_dir_args(dir), None);
+        assert_eq!(SourcePort::Odamex.save_dir_args(dir), None);
+    }
+}
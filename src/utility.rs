@@ -1,7 +1,122 @@
 use rfd::FileDialog;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use zip::read::ZipArchive;
+
+use crate::compat::{self, CompatLevel};
+use crate::idgames::{self, ModInfo};
+use crate::library::Library;
+use crate::source_port::SourcePort;
+use crate::wad::Lump;
+use crate::{iwad, wad};
+
+/// The kinds of files that GZDoom can load and that are kept in the mods directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModKind {
+    Wad,
+    Pk3,
+    Pk7,
+    Ipk3,
+    Deh,
+    Bex,
+}
+
+impl ModKind {
+    /// Every kind, in the order they are listed in the UI
+    pub const ALL: [ModKind; 6] = [
+        ModKind::Wad,
+        ModKind::Pk3,
+        ModKind::Pk7,
+        ModKind::Ipk3,
+        ModKind::Deh,
+        ModKind::Bex,
+    ];
+
+    /// Determines the kind of mod file from its extension (case-insensitive)
+    ///
+    /// #Arguments
+    /// - path to the file
+    ///
+    /// #Returns
+    /// - the ModKind, or None if the file is not a recognized mod file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ModKind> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "wad" => Some(ModKind::Wad),
+            "pk3" => Some(ModKind::Pk3),
+            "pk7" => Some(ModKind::Pk7),
+            "ipk3" => Some(ModKind::Ipk3),
+            "deh" => Some(ModKind::Deh),
+            "bex" => Some(ModKind::Bex),
+            _ => None,
+        }
+    }
+
+    /// Short lowercase label used in the UI
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModKind::Wad => "wad",
+            ModKind::Pk3 => "pk3",
+            ModKind::Pk7 => "pk7",
+            ModKind::Ipk3 => "ipk3",
+            ModKind::Deh => "deh",
+            ModKind::Bex => "bex",
+        }
+    }
+
+    /// Function to read a kind from its label
+    ///
+    /// #Arguments
+    /// - the label, case-insensitive, e.g. "wad"
+    ///
+    /// #Returns
+    /// - the ModKind, or None if the label is unknown
+    pub fn from_label(label: &str) -> Option<ModKind> {
+        let label = label.trim().to_lowercase();
+        ModKind::ALL.into_iter().find(|kind| kind.as_str() == label)
+    }
+
+    /// DeHackEd patches are loaded with -deh instead of -file
+    pub fn is_dehacked(&self) -> bool {
+        matches!(self, ModKind::Deh | ModKind::Bex)
+    }
+}
+
+/// Information gathered about a mod file while scanning the mods directory
+#[derive(Debug, Clone, Serialize)]
+pub struct ModEntry {
+    pub path: String,
+    pub kind: ModKind,
+    pub maps: Vec<String>,
+    /// IWAD file name the mod asks for in its GAMEINFO, e.g. "doom2.wad"
+    pub required_iwad: Option<String>,
+    /// Fields from the idgames text file kept next to the mod
+    pub info: Option<ModInfo>,
+    /// Features the mod needs from the source port
+    pub compat: CompatLevel,
+    /// Folder the file is in relative to the mods directory, e.g. "maps/megawads".
+    /// Empty for files at the top of the mods directory
+    pub category: String,
+    /// Lump directory of WADs, empty for other kinds and for files listed from the library
+    #[serde(skip)]
+    pub lumps: Vec<Lump>,
+}
+
+impl ModEntry {
+    /// Path of the file relative to the mods directory, see `mod_key`
+    pub fn key(&self) -> String {
+        let file_name = Path::new(&self.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        mod_key(&self.category, &file_name)
+    }
+}
 
 /// Function to get a folder path. Used to get the mod directory
 /// #Arguments
@@ -13,39 +128,41 @@ use std::path::PathBuf;
 /// - if canceled or an error - returns an empty string
 pub fn get_folder_path() -> String {
     let folder_path: Option<PathBuf> = FileDialog::new().pick_folder();
-    println!("DEBUG - Selected folder path: {:?}", folder_path);
+    eprintln!("DEBUG - Selected folder path: {:?}", folder_path);
     folder_path
         .map(|path| path.to_string_lossy().into_owned())
         .unwrap_or_default()
 }
 
-/// Function to get the GZDoom Executable Path Only
+/// Function to get the path to a supported source port executable
 /// #Arguments
 /// - none
 ///
 /// Prompts user for path using fileDialog
 /// #Returns
 /// - String to the executable path
-/// - if canceled or an error occurs - returns an error message
-pub fn select_executable() -> String {
-    FileDialog::new()
+/// - None if canceled or the executable is not a supported port
+pub fn select_executable() -> Option<String> {
+    let path = FileDialog::new()
         .add_filter("Executable", &["exe", "sh", "bin", "command", "app"]) // Supports Windows, macOS, Linux
-        .pick_file()
-        .map(|path| {
-            let path_str = path.to_string_lossy().into_owned();
-
-            // Extract file name for comparison
-            if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
-                if file_name == "GZDoom" || file_name == "GZDoom.exe" || file_name == "GZDoom.app" {
-                    println!("DEBUG - Selected executable is {}", path_str);
-                    return path_str; // Return path if it's GZDoom
-                }
-            }
+        .pick_file()?;
+    let path_str = path.to_string_lossy().into_owned();
 
-            // If not GZDoom, return a message instead
-            "The executable selected is not GZDoom".to_string()
-        })
-        .unwrap_or_else(|| "No executable selected.".to_string()) // Handle cancel case
+    // Compare the file name against the known source ports
+    match SourcePort::from_executable(&path) {
+        Some(port) => {
+            eprintln!(
+                "DEBUG - Selected executable is {} ({})",
+                path_str,
+                port.name()
+            );
+            Some(path_str)
+        }
+        None => {
+            eprintln!("{} is not a supported source port", path_str);
+            None
+        }
+    }
 }
 
 /// Function to get the DOOM II wad file
@@ -54,46 +171,114 @@ pub fn select_executable() -> String {
 ///
 /// Prompts user for path using fileDialog
 /// #Returns
-/// - String to the executable path
-/// - if canceled or an error occurs - returns an error message
-pub fn get_wad_path() -> String {
+/// - String to the WAD path
+/// - None if canceled
+pub fn get_wad_path() -> Option<String> {
     let file = FileDialog::new()
         .add_filter("WAD File", &["wad"]) // Supports Windows, macOS, Linux
         .pick_file();
 
-    println!("Selected file: {:?}", file);
+    eprintln!("Selected file: {:?}", file);
 
-    file.map(|path| {
-        let path_str = path.to_string_lossy().into_owned();
-        return path_str;
-    })
-    .unwrap_or_else(|| "No executable selected.".to_string()) // Handle cancel case
+    file.map(|path| path.to_string_lossy().into_owned())
 }
 
-/// Function to retrieve a list of wad files in the mods folder
+/// Function to get an archive to import
+/// #Arguments
+/// - none
+///
+/// Prompts user for path using fileDialog
+/// #Returns
+/// - PathBuf of the archive
+/// - None if canceled
+pub fn get_archive_path() -> Option<PathBuf> {
+    let file = FileDialog::new()
+        .add_filter(
+            "Archives",
+            &["zip", "7z", "tar", "gz", "tgz", "xz", "txz", "bz2", "tbz2"],
+        )
+        .pick_file();
+
+    eprintln!("Selected archive: {:?}", file);
+    file
+}
+
+/// Function to get an existing source port config to use as a base profile
+/// #Arguments
+/// - none
+///
+/// Prompts user for path using fileDialog
+/// #Returns
+/// - PathBuf of the config file
+/// - None if canceled
+pub fn get_port_config_path() -> Option<PathBuf> {
+    let file = FileDialog::new()
+        .add_filter("Port Config", &["ini", "cfg"])
+        .pick_file();
+
+    eprintln!("Selected config: {:?}", file);
+    file
+}
+
+/// Function to get the file to export the user data of the library to
+/// #Arguments
+/// - none
+///
+/// Prompts user for path using fileDialog
+/// #Returns
+/// - PathBuf of the JSON file
+/// - None if canceled
+pub fn get_export_path() -> Option<PathBuf> {
+    let file = FileDialog::new()
+        .add_filter("JSON", &["json"])
+        .set_file_name("imp-porter-user-data.json")
+        .save_file();
+
+    eprintln!("Export file: {:?}", file);
+    file
+}
+
+/// Function to retrieve a list of mod files (wad, pk3, pk7, ipk3, deh, bex) in the mods folder
+/// and its subfolders
 /// #Arguments
 /// - takes a string reference
 ///
 /// #Returns
-/// - HashMap of the file names and their related paths as Strings
+/// - HashMap of the file paths relative to the mods folder and their related ModEntry
+///   (path, kind, maps and category)
 /// - empty if no files exist.
-pub fn get_wad_files_in_folder(folder_path: &str) -> HashMap<String, String> {
+///
+/// Files are read through the library, so only new and changed files are parsed.
+/// If the library can't be opened every file is read again.
+/// Files that are not valid (truncated, corrupt or mislabeled) are skipped.
+pub fn get_mod_files_in_folder(folder_path: &str) -> HashMap<String, ModEntry> {
     let mut file_map = HashMap::new();
 
     if folder_path == "empty" || folder_path.is_empty() {
         return file_map;
     }
 
-    // looping through the directories entries, determining if they're files and if they are wad files
-    if let Ok(entries) = fs::read_dir(folder_path) {
-        for entry in entries.flatten() {
-            if let Ok(file_type) = entry.file_type() {
-                if file_type.is_file() {
-                    if let Some(file_name) = entry.file_name().to_str() {
-                        if file_name.to_lowercase().ends_with(".wad") {
-                            if let Some(file_path) = entry.path().to_str() {
-                                file_map.insert(file_name.to_string(), file_path.to_string());
-                            }
+    match Library::open().and_then(|mut library| library.scan(folder_path)) {
+        Ok(files) => {
+            for file in files {
+                file_map.insert(
+                    relative_path(folder_path, Path::new(&file.entry.path)),
+                    file.entry,
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("Library unavailable, reading every mod file: {}", e);
+            for path in find_mod_files(folder_path) {
+                if let (Some(file_path), Some(kind)) = (path.to_str(), ModKind::from_path(&path)) {
+                    let relative = relative_path(folder_path, &path);
+                    match read_mod_entry(file_path, kind) {
+                        Ok(mut mod_entry) => {
+                            mod_entry.category = category_of(folder_path, &path);
+                            file_map.insert(relative, mod_entry);
+                        }
+                        Err(e) => {
+                            eprintln!("Skipping invalid mod '{}': {}", relative, e);
                         }
                     }
                 }
@@ -103,3 +288,168 @@ pub fn get_wad_files_in_folder(folder_path: &str) -> HashMap<String, String> {
 
     file_map
 }
+
+/// Function to find the mod files in a folder and its subfolders without reading them
+/// #Arguments
+/// - path to the folder as a string reference
+///
+/// #Returns
+/// - Vector of paths to files with a mod file extension
+/// - empty if the folder can't be read
+///
+/// Hidden folders (starting with '.') and symbolic links to folders are not searched,
+/// so a link back to a parent folder can't make the search loop forever.
+pub fn find_mod_files(folder_path: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut folders = vec![PathBuf::from(folder_path)];
+
+    // looping through the directories entries, determining if they're files, folders or mod files
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = fs::read_dir(&folder) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if let Ok(file_type) = entry.file_type() {
+                if file_type.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+                    folders.push(entry.path());
+                } else if file_type.is_file() && ModKind::from_path(entry.file_name()).is_some() {
+                    files.push(entry.path());
+                }
+            }
+        }
+    }
+
+    files
+}
+
+/// Function to get the path of a file relative to the mods folder, with '/' separators
+/// #Arguments
+/// - path to the mods folder as a string reference
+/// - path to a file inside it
+///
+/// #Returns
+/// - String such as "maps/megawads/scythe.wad", or the full path if the file is outside the folder
+pub fn relative_path(folder_path: &str, file_path: &Path) -> String {
+    match file_path.strip_prefix(folder_path) {
+        Ok(relative) => relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => file_path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Function to get the key a mod file's settings, saves and config profile are kept under
+/// #Arguments
+/// - category of the file, the folder it is in relative to the mods folder
+/// - file name of the mod
+///
+/// #Returns
+/// - String such as "maps/megawads/scythe.wad", the same as `relative_path` of the file,
+///   so two mods with the same file name in different folders don't share them
+pub fn mod_key(category: &str, file_name: &str) -> String {
+    if category.is_empty() {
+        file_name.to_string()
+    } else {
+        format!("{}/{}", category, file_name)
+    }
+}
+
+/// Function to get the category of a mod file, the folder it is in relative to the mods folder
+/// #Arguments
+/// - path to the mods folder as a string reference
+/// - path to a file inside it
+///
+/// #Returns
+/// - String such as "maps/megawads", empty for files at the top of the mods folder
+pub fn category_of(folder_path: &str, file_path: &Path) -> String {
+    file_path
+        .parent()
+        .map(|parent| relative_path(folder_path, parent))
+        .unwrap_or_default()
+}
+
+/// Validates a mod file and reads the maps, required IWAD and compatibility level it contains
+///
+/// #Arguments
+/// - path to the mod file as a string reference
+/// - the kind of mod file
+///
+/// #Returns
+/// - ModEntry for the file. Maps are empty for files that can't contain maps
+/// - an error message if the file is corrupt
+///
+/// WADs are parsed with the wad module. pk3/ipk3 files are zips, their maps are the
+/// wads stored under maps/. pk7 files (7z) and DeHackEd patches are not inspected.
+pub fn read_mod_entry(file_path: &str, kind: ModKind) -> Result<ModEntry, String> {
+    let mut mod_entry = ModEntry {
+        path: file_path.to_string(),
+        kind,
+        maps: Vec::new(),
+        required_iwad: None,
+        info: idgames::read_mod_info(Path::new(file_path)),
+        compat: CompatLevel::Vanilla,
+        category: String::new(),
+        lumps: Vec::new(),
+    };
+
+    // a COMPLVL lump states the compatibility level of a WAD
+    let mut complvl = None;
+
+    match kind {
+        ModKind::Wad => {
+            let parsed = wad::read_wad(file_path).map_err(|e| e.to_string())?;
+            mod_entry.maps = parsed.maps();
+
+            if let Some(lump) = parsed.find_lump("GAMEINFO") {
+                let data = wad::read_lump_data(file_path, lump).map_err(|e| e.to_string())?;
+                mod_entry.required_iwad =
+                    iwad::parse_gameinfo_iwad(&String::from_utf8_lossy(&data));
+            }
+
+            if let Some(lump) = parsed.find_lump("COMPLVL") {
+                let data = wad::read_lump_data(file_path, lump).map_err(|e| e.to_string())?;
+                complvl = Some(String::from_utf8_lossy(&data).into_owned());
+            }
+            mod_entry.lumps = parsed.lumps;
+        }
+        ModKind::Pk3 | ModKind::Ipk3 => {
+            let file = File::open(file_path).map_err(|e| e.to_string())?;
+            let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+            mod_entry.maps = archive
+                .file_names()
+                .filter_map(|name| {
+                    let lower = name.to_lowercase();
+                    let stem = lower.strip_prefix("maps/")?.strip_suffix(".wad")?;
+                    (!stem.contains('/')).then(|| stem.to_uppercase())
+                })
+                .collect();
+
+            // GAMEINFO lives in the root of the archive, with or without an extension
+            let gameinfo_name = archive
+                .file_names()
+                .find(|name| {
+                    let lower = name.to_lowercase();
+                    lower == "gameinfo" || lower.starts_with("gameinfo.")
+                })
+                .map(|name| name.to_string());
+
+            if let Some(name) = gameinfo_name {
+                let mut data = Vec::new();
+                archive
+                    .by_name(&name)
+                    .map_err(|e| e.to_string())?
+                    .take(iwad::MAX_GAMEINFO_SIZE)
+                    .read_to_end(&mut data)
+                    .map_err(|e| e.to_string())?;
+                mod_entry.required_iwad =
+                    iwad::parse_gameinfo_iwad(&String::from_utf8_lossy(&data));
+            }
+        }
+        ModKind::Pk7 | ModKind::Deh | ModKind::Bex => {}
+    }
+
+    mod_entry.compat = compat::detect_compat(kind, &mod_entry.lumps, complvl.as_deref());
+    Ok(mod_entry)
+}
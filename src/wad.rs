@@ -0,0 +1,354 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Size in bytes of the header at the start of every WAD file
+const HEADER_SIZE: u64 = 12;
+
+/// Size in bytes of a single entry in the lump directory
+const DIRECTORY_ENTRY_SIZE: u64 = 16;
+
+/// The two kinds of WAD files. IWADs are full games (Doom II, Heretic, ...),
+/// PWADs are patches/mods that get loaded on top of an IWAD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WadKind {
+    Iwad,
+    Pwad,
+}
+
+/// A single entry in the WAD lump directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lump {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// The parsed header and lump directory of a WAD file
+#[derive(Debug, Clone)]
+pub struct Wad {
+    pub kind: WadKind,
+    pub lumps: Vec<Lump>,
+}
+
+/// Lumps that can directly follow a map marker. Doom/Hexen format maps start with
+/// THINGS (some editors write LINEDEFS first), UDMF maps start with TEXTMAP.
+const MAP_START_LUMPS: [&str; 3] = ["THINGS", "LINEDEFS", "TEXTMAP"];
+
+/// Every lump that belongs to a map after its marker, in Doom, Hexen and UDMF format.
+/// None of them can be a map marker themselves.
+const MAP_DATA_LUMPS: [&str; 18] = [
+    "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS", "NODES", "SECTORS", "REJECT",
+    "BLOCKMAP", "BEHAVIOR", "SCRIPTS", "TEXTMAP", "ZNODES", "DIALOGUE", "LEAFS", "LIGHTS",
+    "ENDMAP",
+];
+
+/// Errors that can occur while reading a WAD file
+#[derive(Debug)]
+pub enum WadError {
+    /// The file could not be opened or read
+    Io(std::io::Error),
+    /// The file does not start with "IWAD" or "PWAD"
+    BadMagic([u8; 4]),
+    /// The file is smaller than the header or the directory it claims to have
+    Truncated { expected: u64, actual: u64 },
+    /// A lump in the directory points outside of the file
+    LumpOutOfBounds {
+        name: String,
+        offset: u32,
+        size: u32,
+    },
+}
+
+impl fmt::Display for WadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WadError::Io(e) => write!(f, "I/O error: {}", e),
+            WadError::BadMagic(magic) => write!(
+                f,
+                "not a WAD file (header starts with {:?})",
+                String::from_utf8_lossy(magic)
+            ),
+            WadError::Truncated { expected, actual } => write!(
+                f,
+                "WAD file is truncated (expected at least {} bytes, found {})",
+                expected, actual
+            ),
+            WadError::LumpOutOfBounds { name, offset, size } => write!(
+                f,
+                "lump '{}' (offset {}, size {}) lies outside of the file",
+                name, offset, size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for WadError {
+    fn from(e: std::io::Error) -> Self {
+        WadError::Io(e)
+    }
+}
+
+impl Wad {
+    /// Finds the map marker lumps in the directory (MAPxx, ExMy or any custom name)
+    ///
+    /// #Arguments
+    /// - none
+    ///
+    /// #Returns
+    /// - Vector of map names in directory order
+    /// - empty if the WAD has no maps (gameplay mods, music packs, ...)
+    pub fn maps(&self) -> Vec<String> {
+        self.lumps
+            .windows(2)
+            .filter(|pair| {
+                MAP_START_LUMPS.contains(&pair[1].name.as_str())
+                    && !MAP_DATA_LUMPS.contains(&pair[0].name.as_str())
+            })
+            .map(|pair| pair[0].name.clone())
+            .collect()
+    }
+
+    /// Finds the last lump with the given name, which is the one the game would use
+    ///
+    /// #Arguments
+    /// - name of the lump as a string reference
+    ///
+    /// #Returns
+    /// - the matching lump, or None if the WAD does not contain it
+    pub fn find_lump(&self, name: &str) -> Option<&Lump> {
+        self.lumps
+            .iter()
+            .rev()
+            .find(|lump| lump.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Builds a short, human readable summary of a map list such as "32 maps, MAP01–MAP32"
+///
+/// #Arguments
+/// - slice of map names in directory order
+///
+/// #Returns
+/// - String summary, "No maps" if the list is empty
+pub fn describe_maps(maps: &[String]) -> String {
+    match maps {
+        [] => "No maps".to_string(),
+        [only] => format!("1 map, {}", only),
+        [first, .., last] => format!("{} maps, {}–{}", maps.len(), first, last),
+    }
+}
+
+/// Function to open a WAD file and read its header and lump directory
+///
+/// #Arguments
+/// - path to the wad file
+///
+/// #Returns
+/// - the parsed Wad with every lump entry in directory order
+/// - WadError if the file is missing, not a WAD, truncated or corrupt
+pub fn read_wad<P: AsRef<Path>>(path: P) -> Result<Wad, WadError> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    parse_wad(BufReader::new(file), file_len)
+}
+
+/// Parses the header and lump directory from any seekable reader
+///
+/// #Arguments
+/// - reader positioned anywhere in the WAD data
+/// - total length of the WAD data in bytes
+///
+/// #Returns
+/// - the parsed Wad, or a WadError describing why it was rejected
+pub fn parse_wad<R: Read + Seek>(mut reader: R, file_len: u64) -> Result<Wad, WadError> {
+    if file_len < HEADER_SIZE {
+        return Err(WadError::Truncated {
+            expected: HEADER_SIZE,
+            actual: file_len,
+        });
+    }
+
+    let mut header = [0u8; HEADER_SIZE as usize];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+
+    let magic = [header[0], header[1], header[2], header[3]];
+    let kind = match &magic {
+        b"IWAD" => WadKind::Iwad,
+        b"PWAD" => WadKind::Pwad,
+        _ => return Err(WadError::BadMagic(magic)),
+    };
+
+    let lump_count = read_u32(&header[4..8]) as u64;
+    let directory_offset = read_u32(&header[8..12]) as u64;
+
+    // the whole directory must fit inside the file
+    let directory_end = directory_offset + lump_count * DIRECTORY_ENTRY_SIZE;
+    if directory_end > file_len {
+        return Err(WadError::Truncated {
+            expected: directory_end,
+            actual: file_len,
+        });
+    }
+
+    reader.seek(SeekFrom::Start(directory_offset))?;
+    let mut directory = vec![0u8; (lump_count * DIRECTORY_ENTRY_SIZE) as usize];
+    reader.read_exact(&mut directory)?;
+
+    let mut lumps = Vec::with_capacity(lump_count as usize);
+    for entry in directory.chunks_exact(DIRECTORY_ENTRY_SIZE as usize) {
+        let offset = read_u32(&entry[0..4]);
+        let size = read_u32(&entry[4..8]);
+        let name = read_lump_name(&entry[8..16]);
+
+        if offset as u64 + size as u64 > file_len {
+            return Err(WadError::LumpOutOfBounds { name, offset, size });
+        }
+
+        lumps.push(Lump { name, offset, size });
+    }
+
+    Ok(Wad { kind, lumps })
+}
+
+/// Function to read the raw data of a single lump out of a WAD file
+///
+/// #Arguments
+/// - path to the wad file the lump came from
+/// - the lump entry from that file's directory
+///
+/// #Returns
+/// - the bytes of the lump
+/// - WadError if the file can no longer be read
+pub fn read_lump_data<P: AsRef<Path>>(path: P, lump: &Lump) -> Result<Vec<u8>, WadError> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(lump.offset as u64))?;
+
+    let mut data = vec![0u8; lump.size as usize];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Reads a little-endian u32 from the first four bytes of a slice
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Lump names are 8 bytes, padded with NULs when shorter
+fn read_lump_name(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Builds a WAD in memory with empty lumps of the given names
+    fn build_wad(magic: &[u8; 4], names: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(magic);
+        data.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        for name in names {
+            data.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            let mut padded = [0u8; 8];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+            data.extend_from_slice(&padded);
+        }
+        data
+    }
+
+    fn parse(data: Vec<u8>) -> Result<Wad, WadError> {
+        let len = data.len() as u64;
+        parse_wad(Cursor::new(data), len)
+    }
+
+    #[test]
+    fn finds_doom_format_maps() {
+        let map = [
+            "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS", "NODES", "SECTORS",
+            "REJECT", "BLOCKMAP",
+        ];
+        let mut names = vec!["DEHACKED", "MAP01"];
+        names.extend(map);
+        names.push("MAP02");
+        names.extend(map);
+        names.push("D_RUNNIN");
+
+        let wad = parse(build_wad(b"PWAD", &names)).unwrap();
+        assert_eq!(wad.kind, WadKind::Pwad);
+        assert_eq!(wad.maps(), vec!["MAP01", "MAP02"]);
+        assert_eq!(describe_maps(&wad.maps()), "2 maps, MAP01–MAP02");
+    }
+
+    #[test]
+    fn finds_maps_starting_with_linedefs() {
+        let names = [
+            "E1M1", "LINEDEFS", "THINGS", "SIDEDEFS", "E1M2", "THINGS", "LINEDEFS",
+        ];
+        let wad = parse(build_wad(b"IWAD", &names)).unwrap();
+        assert_eq!(wad.maps(), vec!["E1M1", "E1M2"]);
+    }
+
+    #[test]
+    fn finds_udmf_maps() {
+        let names = [
+            "MAP01", "TEXTMAP", "ZNODES", "ENDMAP", "MYLEVEL", "TEXTMAP", "BEHAVIOR", "ENDMAP",
+        ];
+        let wad = parse(build_wad(b"PWAD", &names)).unwrap();
+        assert_eq!(wad.maps(), vec!["MAP01", "MYLEVEL"]);
+    }
+
+    #[test]
+    fn wad_without_maps() {
+        let wad = parse(build_wad(b"PWAD", &["DEHACKED", "D_RUNNIN"])).unwrap();
+        assert!(wad.maps().is_empty());
+        assert_eq!(describe_maps(&wad.maps()), "No maps");
+        assert_eq!(wad.find_lump("dehacked").unwrap().name, "DEHACKED");
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        assert!(matches!(
+            parse(build_wad(b"PK\x03\x04", &[])),
+            Err(WadError::BadMagic(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        assert!(matches!(
+            parse(b"PWAD\x01\x00".to_vec()),
+            Err(WadError::Truncated {
+                expected: 12,
+                actual: 6
+            })
+        ));
+
+        let mut data = build_wad(b"PWAD", &["MAP01", "THINGS"]);
+        data.truncate(data.len() - 4);
+        assert!(matches!(parse(data), Err(WadError::Truncated { .. })));
+    }
+
+    #[test]
+    fn rejects_lumps_outside_the_file() {
+        let mut data = build_wad(b"PWAD", &["THINGS"]);
+        // size of the only lump
+        data[16..20].copy_from_slice(&1000u32.to_le_bytes());
+        assert!(matches!(parse(data), Err(WadError::LumpOutOfBounds { .. })));
+    }
+}